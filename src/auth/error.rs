@@ -1,12 +1,4 @@
-use axum::{
-    response::{IntoResponse, Response},
-    http::StatusCode,
-    Json,
-};
-use serde_json::json;
-
-// AI: This AuthError will be expanded in Phase 4.1 (Centralized Error Handling)
-// For now, it focuses on JWT validation errors.
+// JWT validation errors. Rendered through `AppError` (see `crate::error`).
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
     #[error("Authorization token not found")]
@@ -24,35 +16,42 @@ pub enum AuthError {
     #[error("Token claim invalid: {claim} - {reason}")]
     TokenClaimInvalid { claim: String, reason: String },
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Could not find key to verify token signature (JWK with kid {kid} not found)")]
     JwkKidNotFound { kid: String },
 
+    #[error("Signing keys are not loaded yet")]
+    KeysNotReady,
+
     #[error("Internal error during JWKS processing: {0}")]
     JwksProcessingError(#[from] crate::auth::jwks::JwksError),
     
-    #[error("Required environment variable for validation not set: {0}")]
-    MissingEnvVar(String),
-    
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthError::MissingToken => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::InvalidTokenFormat => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::InvalidToken(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token has expired".to_string()),
-            AuthError::TokenClaimInvalid { .. } => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::JwkKidNotFound { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Could not verify token (key not found)".to_string()),
-            AuthError::JwksProcessingError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Error processing signing keys".to_string()),
-            AuthError::MissingEnvVar(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Server configuration error for auth".to_string()),
-            AuthError::InternalError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()),
-        };
+/// Response extension naming the `AuthError` kind a response was rendered from, so
+/// `jwt_auth_middleware` also counts rejections raised by extractors like `AdminUser`.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthFailure(pub &'static str);
 
-        let body = Json(json!({ "error": error_message }));
-        (status, body).into_response()
+impl AuthError {
+    /// Variant name, used as a low-cardinality metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthError::MissingToken => "missing_token",
+            AuthError::InvalidTokenFormat => "invalid_token_format",
+            AuthError::InvalidToken(_) => "invalid_token",
+            AuthError::TokenExpired => "token_expired",
+            AuthError::TokenClaimInvalid { .. } => "token_claim_invalid",
+            AuthError::Forbidden(_) => "forbidden",
+            AuthError::JwkKidNotFound { .. } => "jwk_kid_not_found",
+            AuthError::KeysNotReady => "keys_not_ready",
+            AuthError::JwksProcessingError(_) => "jwks_processing_error",
+            AuthError::InternalError(_) => "internal_error",
+        }
     }
 }
 
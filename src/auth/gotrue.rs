@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+
+/// GoTrue's convention for an indefinite ban (roughly 100 years).
+pub const PERMANENT_BAN_DURATION: &str = "876000h";
+
+/// Errors returned by the Supabase Auth (GoTrue) admin API client.
+#[derive(Debug, thiserror::Error)]
+pub enum GoTrueError {
+    #[error("Auth admin API is not configured")]
+    NotConfigured,
+
+    #[error("Network error calling the auth admin API: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("Auth user not found")]
+    UserNotFound,
+
+    #[error("Auth admin API returned {status}: {message}")]
+    Api { status: StatusCode, message: String },
+}
+
+/// A user record as returned by the GoTrue admin API (`auth.users`).
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AuthAdminUser {
+    pub id: Uuid,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub app_metadata: Value,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub user_metadata: Value,
+    #[serde(default)]
+    pub banned_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub email_confirmed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_sign_in_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Attributes accepted by `POST /admin/users`.
+#[derive(Debug, Serialize)]
+pub struct NewAuthUser {
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub email_confirm: bool,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub app_metadata: Value,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub user_metadata: Value,
+}
+
+/// One page of users from `GET /admin/users`.
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct AuthUserPage {
+    pub users: Vec<AuthAdminUser>,
+    /// Total number of users, taken from the `x-total-count` response header.
+    pub total: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ListUsersResponse {
+    users: Vec<AuthAdminUser>,
+}
+
+/// Thin client for the GoTrue admin endpoints, authenticated with the service role key.
+/// The service role key bypasses RLS and must never be exposed to clients.
+#[derive(Clone)]
+pub struct GoTrueAdminClient {
+    http: Client,
+    base_url: String,
+    service_key: String,
+}
+
+impl GoTrueAdminClient {
+    pub fn new(http: Client, supabase_url: &str, service_key: String) -> Self {
+        Self {
+            http,
+            base_url: format!("{}/auth/v1", supabase_url.trim_end_matches('/')),
+            service_key,
+        }
+    }
+
+    /// Builds a client from `auth.supabase_url` and `auth.service_role_key`, or
+    /// returns `None` when they are not configured.
+    pub fn from_config(config: &AuthConfig, http: Client) -> Option<Self> {
+        let supabase_url = config.supabase_url.as_deref()?;
+        let service_key = config.service_role_key.clone()?;
+        Some(Self::new(http, supabase_url, service_key))
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("apikey", &self.service_key)
+            .bearer_auth(&self.service_key)
+    }
+
+    /// Lists users, `page` is 1-based.
+    pub async fn list_users(&self, page: u32, per_page: u32) -> Result<AuthUserPage, GoTrueError> {
+        let response = self
+            .request(Method::GET, "/admin/users")
+            .query(&[("page", page), ("per_page", per_page)])
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+        let total = response
+            .headers()
+            .get("x-total-count")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let body: ListUsersResponse = response.json().await?;
+        Ok(AuthUserPage { users: body.users, total })
+    }
+
+    pub async fn create_user(&self, user: &NewAuthUser) -> Result<AuthAdminUser, GoTrueError> {
+        send_json(self.request(Method::POST, "/admin/users").json(user)).await
+    }
+
+    /// Bans a user for `duration` (e.g. "24h"); pass "none" to lift an existing ban.
+    pub async fn set_ban_duration(&self, user_id: Uuid, duration: &str) -> Result<AuthAdminUser, GoTrueError> {
+        send_json(
+            self.request(Method::PUT, &format!("/admin/users/{}", user_id))
+                .json(&json!({ "ban_duration": duration })),
+        )
+        .await
+    }
+
+    /// Updates `app_metadata`. GoTrue merges top-level keys into the existing object
+    /// and removes keys whose value is `null`.
+    pub async fn update_app_metadata(&self, user_id: Uuid, app_metadata: Value) -> Result<AuthAdminUser, GoTrueError> {
+        send_json(
+            self.request(Method::PUT, &format!("/admin/users/{}", user_id))
+                .json(&json!({ "app_metadata": app_metadata })),
+        )
+        .await
+    }
+
+    pub async fn delete_user(&self, user_id: Uuid) -> Result<(), GoTrueError> {
+        let response = self
+            .request(Method::DELETE, &format!("/admin/users/{}", user_id))
+            .send()
+            .await?;
+        check_status(response).await?;
+        Ok(())
+    }
+}
+
+async fn send_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, GoTrueError> {
+    let response = check_status(request.send().await?).await?;
+    Ok(response.json().await?)
+}
+
+async fn check_status(response: Response) -> Result<Response, GoTrueError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(GoTrueError::UserNotFound);
+    }
+
+    // GoTrue has used `msg`, `message` and `error_description` for the human readable text.
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let message = ["msg", "message", "error_description", "error"]
+        .iter()
+        .find_map(|key| body[*key].as_str())
+        .unwrap_or("unknown error")
+        .to_string();
+    Err(GoTrueError::Api { status, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{mock_gotrue, SERVICE_KEY};
+
+    fn client(url: &str, key: &str) -> GoTrueAdminClient {
+        GoTrueAdminClient::new(Client::new(), &format!("{}/", url), key.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_list_users_reads_total_from_header() {
+        let mock = mock_gotrue(Some("42")).await;
+        let page = client(&mock.url, SERVICE_KEY).list_users(2, 10).await.unwrap();
+        assert_eq!(page.users.len(), 1);
+        assert_eq!(page.total, Some(42));
+        assert_eq!(mock.requests(), ["GET /auth/v1/admin/users?page=2&per_page=10"]);
+
+        for total in [None, Some("many")] {
+            let mock = mock_gotrue(total).await;
+            let page = client(&mock.url, SERVICE_KEY).list_users(1, 10).await.unwrap();
+            assert_eq!(page.total, None, "x-total-count: {:?}", total);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_status_and_message() {
+        let mock = mock_gotrue(None).await;
+        let err = client(&mock.url, "wrong-key").list_users(1, 10).await.unwrap_err();
+        assert!(
+            matches!(&err, GoTrueError::Api { status, message } if *status == StatusCode::UNAUTHORIZED && message == "invalid api key"),
+            "{:?}",
+            err
+        );
+
+        let err = client(&mock.url, SERVICE_KEY).set_ban_duration(Uuid::new_v4(), "24h").await.unwrap_err();
+        assert!(matches!(err, GoTrueError::UserNotFound), "{:?}", err);
+    }
+}
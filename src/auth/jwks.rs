@@ -1,25 +1,99 @@
-use jsonwebtoken::jwk::JwkSet;
-use tokio::sync::OnceCell;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::DecodingKey;
 use reqwest::Client;
-use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::AuthConfig;
+use crate::metrics::Metrics;
+use crate::shutdown::Shutdown;
 
 // AI: Define a specific error type for JWKS fetching, or use a general AppError (Phase 4.1)
-#[derive(Debug, thiserror::Error)] // AI: Placeholder, requires thiserror crate
+#[derive(Debug, thiserror::Error)]
 pub enum JwksError {
     #[error("Network error fetching JWKS: {0}")]
     Network(#[from] reqwest::Error),
     #[error("Invalid JWKS URL: {0}")]
     InvalidUrl(String),
-    #[error("JWKS_URL not set in environment")]
+    #[error("No JWKS source configured (SUPABASE_JWKS_URL, SUPABASE_JWKS_FILE or SUPABASE_JWKS_JSON)")]
     UrlNotSet,
-    // AI: Add more error variants as needed (e.g., parsing error if not covered by reqwest::Error)
+    #[error("JWKS not loaded yet")]
+    NotLoaded,
+    #[error("Could not read or write JWKS file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JWKS JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("JWKS contains no usable keys: {0}")]
+    NoUsableKeys(String),
+}
+
+/// Where the signing keys are loaded from.
+#[derive(Debug, Clone)]
+pub enum JwksSource {
+    /// Fetched from the Supabase JWKS endpoint and refreshed periodically.
+    Url(String),
+    /// Read from a local file, reloaded when the file changes.
+    File(PathBuf),
+    /// Inline JSON, for air-gapped deployments without a writable filesystem.
+    Inline(String),
+}
+
+impl JwksSource {
+    /// Picks the source from `jwks_json`, `jwks_file` or `jwks_url`, in that order
+    /// of precedence.
+    pub fn from_config(config: &AuthConfig) -> Result<Self, JwksError> {
+        if let Some(json) = &config.jwks_json {
+            Ok(JwksSource::Inline(json.clone()))
+        } else if let Some(path) = &config.jwks_file {
+            Ok(JwksSource::File(path.clone()))
+        } else {
+            config.jwks_url.clone().map(JwksSource::Url).ok_or(JwksError::UrlNotSet)
+        }
+    }
+}
+
+/// Shared handle to the currently installed signing keys. Cloning is cheap; all
+/// clones see keys installed by the background refresh.
+#[derive(Clone, Default)]
+pub struct KeyStore {
+    keys: Arc<RwLock<Option<LoadedKeys>>>,
+}
+
+#[derive(Clone)]
+struct LoadedKeys {
+    jwks: Arc<JwkSet>,
+    loaded_at: Instant,
+}
+
+impl KeyStore {
+    /// Returns the currently installed key set, or `JwksError::NotLoaded` until the
+    /// first fetch (or snapshot load) has succeeded.
+    pub fn get(&self) -> Result<Arc<JwkSet>, JwksError> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.as_ref().map(|loaded| loaded.jwks.clone()).ok_or(JwksError::NotLoaded)
+    }
+
+    pub fn install(&self, jwks: JwkSet) {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        *keys = Some(LoadedKeys { jwks: Arc::new(jwks), loaded_at: Instant::now() });
+    }
+
+    /// Time since keys were last installed, or `None` if none are loaded.
+    pub fn age(&self) -> Option<Duration> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.as_ref().map(|loaded| loaded.loaded_at.elapsed())
+    }
 }
 
-static CACHED_JWKS: OnceCell<JwkSet> = OnceCell::const_new();
+// Backoff for the initial fetch: 0.5s, 1s, 2s, ... capped at 30s, retried until it succeeds.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 async fn fetch_jwks_from_url(jwks_url: &str, client: &Client) -> Result<JwkSet, JwksError> {
+    let url = reqwest::Url::parse(jwks_url).map_err(|e| JwksError::InvalidUrl(e.to_string()))?;
     let jwks: JwkSet = client
-        .get(jwks_url)
+        .get(url)
         .send()
         .await?
         .error_for_status()? // Ensure we have a success status
@@ -28,18 +102,200 @@ async fn fetch_jwks_from_url(jwks_url: &str, client: &Client) -> Result<JwkSet,
     Ok(jwks)
 }
 
-pub async fn get_jwks() -> Result<&'static JwkSet, JwksError> {
-    CACHED_JWKS.get_or_try_init(|| async {
-        let jwks_url = env::var("SUPABASE_JWKS_URL")
-            .map_err(|_| JwksError::UrlNotSet)?;
-        // AI: Consider creating a single reqwest::Client and reusing it (e.g., via AppState or OnceCell)
-        let client = Client::new(); 
-        println!("Fetching JWKS from: {}", jwks_url);
-        fetch_jwks_from_url(&jwks_url, &client).await
-    }).await
+/// Fetches the key set from the Supabase JWKS endpoint.
+pub async fn fetch_jwks(jwks_url: &str, client: &Client) -> Result<JwkSet, JwksError> {
+    tracing::debug!(%jwks_url, "Fetching JWKS");
+    let jwks = fetch_jwks_from_url(jwks_url, client).await?;
+    validate_jwks(&jwks)?;
+    Ok(jwks)
+}
+
+/// Builds the decoding key for a JWK. Shared by the auth middleware and `validate_jwks`
+/// so a key accepted at load time is also usable for verification.
+pub fn decoding_key(jwk: &Jwk) -> Result<DecodingKey, jsonwebtoken::errors::Error> {
+    // Octet keys carry a base64url-encoded secret (`k`). `from_jwk` decodes it; using the
+    // `k` text itself as the HMAC secret would reject every HS256 token.
+    DecodingKey::from_jwk(jwk)
+}
+
+/// Checks that at least one key has a `kid` and valid key material. Unusable keys
+/// are reported but tolerated as long as one usable key remains.
+pub fn validate_jwks(jwks: &JwkSet) -> Result<(), JwksError> {
+    let mut usable = 0;
+    let mut problems = Vec::new();
+    for jwk in &jwks.keys {
+        match (&jwk.common.key_id, decoding_key(jwk)) {
+            (None, _) => problems.push("key without 'kid'".to_string()),
+            (Some(kid), Err(e)) => problems.push(format!("key '{}': {}", kid, e)),
+            (Some(_), Ok(_)) => usable += 1,
+        }
+    }
+
+    if usable == 0 {
+        let reason = if problems.is_empty() { "key set is empty".to_string() } else { problems.join("; ") };
+        return Err(JwksError::NoUsableKeys(reason));
+    }
+    for problem in problems {
+        tracing::warn!(%problem, "Ignoring unusable JWK");
+    }
+    Ok(())
+}
+
+/// Parses and validates a key set from JSON.
+pub fn parse_jwks(json: &[u8]) -> Result<JwkSet, JwksError> {
+    let jwks: JwkSet = serde_json::from_slice(json)?;
+    validate_jwks(&jwks)?;
+    Ok(jwks)
+}
+
+/// Reads and validates a key set from a file (a snapshot or a static JWKS file).
+pub fn load_jwks_file(path: &Path) -> Result<JwkSet, JwksError> {
+    parse_jwks(&std::fs::read(path)?)
+}
+
+/// Writes the snapshot atomically (temp file + rename) so a crash never leaves a torn file.
+pub fn save_snapshot(path: &Path, jwks: &JwkSet) -> Result<(), JwksError> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(jwks)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
 }
 
-// AI: Add logic for JWKS refresh if needed, based on cache-control headers or a fixed interval.
+/// Installs the on-disk snapshot so protected routes can serve requests while the
+/// network fetch is still pending. Returns whether keys were loaded.
+pub fn load_snapshot_on_boot(keys: &KeyStore, path: &Path) -> bool {
+    match load_jwks_file(path) {
+        Ok(jwks) => {
+            tracing::info!(path = %path.display(), "Loaded last-known-good JWKS snapshot");
+            keys.install(jwks);
+            true
+        }
+        Err(JwksError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring JWKS snapshot");
+            false
+        }
+    }
+}
+
+async fn refresh_once(keys: &KeyStore, jwks_url: &str, client: &Client, snapshot_path: Option<&Path>) -> Result<(), JwksError> {
+    let jwks = fetch_jwks(jwks_url, client).await?;
+    if let Some(path) = snapshot_path
+        && let Err(e) = save_snapshot(path, &jwks)
+    {
+        tracing::warn!(path = %path.display(), error = %e, "Failed to write JWKS snapshot");
+    }
+    keys.install(jwks);
+    Ok(())
+}
+
+/// Loads the keys from the configured source. For a URL source this installs the
+/// snapshot (if any) and starts the background fetch; file and inline sources are
+/// loaded and validated immediately, so a bad key set fails startup. Background
+/// tasks stop when `shutdown` is triggered.
+pub fn init(
+    config: &AuthConfig,
+    keys: &KeyStore,
+    client: &Client,
+    metrics: &Arc<Metrics>,
+    shutdown: &Shutdown,
+) -> Result<(), JwksError> {
+    match JwksSource::from_config(config)? {
+        JwksSource::Url(jwks_url) => {
+            let snapshot_path = config.jwks_cache_path.clone();
+            if !snapshot_path.as_deref().is_some_and(|path| load_snapshot_on_boot(keys, path)) {
+                tracing::info!("No JWKS snapshot available; protected routes return 503 until keys are fetched");
+            }
+            let refresh = run_jwks_refresh(
+                keys.clone(),
+                client.clone(),
+                metrics.clone(),
+                jwks_url,
+                snapshot_path,
+                config.jwks_refresh_interval(),
+            );
+            tokio::spawn(shutdown.clone().run_until(refresh));
+        }
+        JwksSource::File(path) => {
+            keys.install(load_jwks_file(&path)?);
+            tracing::info!(path = %path.display(), "Loaded JWKS from file");
+            let watch = watch_jwks_file(keys.clone(), metrics.clone(), path, config.jwks_file_poll_interval());
+            tokio::spawn(shutdown.clone().run_until(watch));
+        }
+        JwksSource::Inline(json) => {
+            keys.install(parse_jwks(json.as_bytes())?);
+            tracing::info!("Loaded inline JWKS");
+        }
+    }
+    Ok(())
+}
+
+/// Background task: fetches the JWKS with exponential backoff until the first
+/// success, then refreshes it every `interval`. Until keys are loaded, protected
+/// routes answer 503.
+async fn run_jwks_refresh(
+    keys: KeyStore,
+    client: Client,
+    metrics: Arc<Metrics>,
+    jwks_url: String,
+    snapshot_path: Option<PathBuf>,
+    interval: Duration,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let result = refresh_once(&keys, &jwks_url, &client, snapshot_path.as_deref()).await;
+        metrics.jwks_refresh(result.is_ok());
+        match result {
+            Ok(()) => {
+                tracing::info!("Fetched and cached JWKS");
+                break;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, retry_in = ?backoff, "Failed to fetch JWKS");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+
+    loop {
+        tokio::time::sleep(interval).await;
+        // Keep serving the previous keys if a refresh fails.
+        let result = refresh_once(&keys, &jwks_url, &client, snapshot_path.as_deref()).await;
+        metrics.jwks_refresh(result.is_ok());
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "Failed to refresh JWKS, keeping previous keys");
+        }
+    }
+}
+
+/// Background task: polls the JWKS file's modification time every `interval` and
+/// reloads rotated keys. Polling also catches the symlink swaps used by Kubernetes
+/// ConfigMap and Secret volumes.
+async fn watch_jwks_file(keys: KeyStore, metrics: Arc<Metrics>, path: PathBuf, interval: Duration) {
+    let modified = |path: &Path| -> Option<SystemTime> { std::fs::metadata(path).and_then(|m| m.modified()).ok() };
+
+    let mut last_modified = modified(&path);
+    loop {
+        tokio::time::sleep(interval).await;
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        // A half-written or invalid file must not replace working keys.
+        let result = load_jwks_file(&path);
+        metrics.jwks_refresh(result.is_ok());
+        match result {
+            Ok(jwks) => {
+                keys.install(jwks);
+                tracing::info!(path = %path.display(), "Reloaded JWKS from file");
+            }
+            Err(e) => tracing::warn!(path = %path.display(), error = %e, "Failed to reload JWKS, keeping previous keys"),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -50,21 +306,154 @@ mod tests {
     // For now, it checks if the function attempts to fetch and fails if URL is not set.
     #[tokio::test]
     async fn test_get_jwks_url_not_set() {
-        env::remove_var("SUPABASE_JWKS_URL");
-        let result = get_jwks().await;
+        let result = JwksSource::from_config(&AuthConfig::default());
         assert!(matches!(result, Err(JwksError::UrlNotSet)));
     }
 
-    // AI: Add more tests: successful fetch, caching behavior, error handling for network issues.
-    // Example for a successful fetch test (requires a mock server or a valid SUPABASE_JWKS_URL):
-    // #[tokio::test]
-    // async fn test_get_jwks_success() {
-    //     // Mock the env var and potentially the HTTP request
-    //     env::set_var("SUPABASE_JWKS_URL", "your_mock_jwks_url_or_real_one_for_testing");
-    //     let jwks = get_jwks().await.expect("Failed to get JWKS");
-    //     assert!(!jwks.keys.is_empty());
-    //     // Test caching: second call should be faster and not print "Fetching JWKS..." again if logging is added to fetch_jwks_from_url
-    //     let jwks_cached = get_jwks().await.expect("Failed to get JWKS from cache");
-    //     assert_eq!(jwks.keys.len(), jwks_cached.keys.len());
-    // }
-} 
\ No newline at end of file
+    #[test]
+    fn test_parse_jwks_rejects_unusable_key_sets() {
+        assert!(matches!(parse_jwks(br#"{"keys":[]}"#), Err(JwksError::NoUsableKeys(_))));
+        // A key without a kid can never be selected by a token header.
+        let no_kid = br#"{"keys":[{"kty":"oct","alg":"HS256","k":"c2VjcmV0"}]}"#;
+        assert!(matches!(parse_jwks(no_kid), Err(JwksError::NoUsableKeys(_))));
+        assert!(matches!(parse_jwks(b"not json"), Err(JwksError::InvalidJson(_))));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let jwks: JwkSet = serde_json::from_str(
+            r#"{"keys":[{"kty":"oct","kid":"test","alg":"HS256","k":"c2VjcmV0"}]}"#,
+        )
+        .unwrap();
+        let path = env::temp_dir().join(format!("jwks-snapshot-{}.json", std::process::id()));
+
+        save_snapshot(&path, &jwks).unwrap();
+        let loaded = load_jwks_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.find("test").is_some());
+    }
+
+    #[test]
+    fn test_octet_key_secret_is_base64url_decoded() {
+        use jsonwebtoken::{decode, encode, EncodingKey, Header, Validation};
+        use serde_json::{json, Value};
+
+        // "k" is "secret" base64url encoded, with a `-` and `_` that plain base64 lacks.
+        let jwk: Jwk = serde_json::from_value(json!({"kty": "oct", "kid": "test", "alg": "HS256", "k": "c2VjcmV0-_8"})).unwrap();
+        let key = decoding_key(&jwk).unwrap();
+        let mut validation = Validation::default();
+        validation.required_spec_claims.clear();
+
+        let claims = json!({"sub": "user-1"});
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret\xfb\xff")).unwrap();
+        assert_eq!(decode::<Value>(&token, &key, &validation).unwrap().claims, claims);
+        // Signing with the encoded text itself must not verify.
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"c2VjcmV0-_8")).unwrap();
+        assert!(decode::<Value>(&token, &key, &validation).is_err());
+    }
+
+    const KEYS_A: &str = r#"{"keys":[{"kty":"oct","kid":"a","alg":"HS256","k":"c2VjcmV0"}]}"#;
+    const KEYS_B: &str = r#"{"keys":[{"kty":"oct","kid":"b","alg":"HS256","k":"c2VjcmV0"}]}"#;
+
+    /// Serves `responses` in order from a local JWKS endpoint, repeating the last one.
+    async fn mock_jwks(responses: Vec<(u16, &'static str)>) -> String {
+        use axum::{http::StatusCode, Router};
+        use std::sync::Mutex;
+
+        let responses = Arc::new(Mutex::new(responses));
+        let app = Router::new().fallback(move || {
+            let responses = responses.clone();
+            async move {
+                let mut responses = responses.lock().unwrap();
+                let (status, body) = if responses.len() > 1 { responses.remove(0) } else { responses[0] };
+                (StatusCode::from_u16(status).unwrap(), [("content-type", "application/json")], body)
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/auth/v1/.well-known/jwks.json", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    async fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        let waited = tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        waited.await.unwrap_or_else(|_| panic!("timed out waiting for {}", what));
+    }
+
+    fn has_key(keys: &KeyStore, kid: &str) -> bool {
+        keys.get().is_ok_and(|jwks| jwks.find(kid).is_some())
+    }
+
+    #[tokio::test]
+    async fn test_initial_fetch_is_retried_until_keys_load() {
+        let url = mock_jwks(vec![(503, "{}"), (200, KEYS_A)]).await;
+        let (keys, metrics) = (KeyStore::default(), Arc::new(Metrics::new()));
+        let refresh = run_jwks_refresh(keys.clone(), Client::new(), metrics.clone(), url, None, Duration::from_secs(3600));
+        let task = tokio::spawn(refresh);
+
+        wait_until("keys", || has_key(&keys, "a")).await;
+        assert_eq!(metrics.jwks_refresh_count(false), 1);
+        assert_eq!(metrics.jwks_refresh_count(true), 1);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_keeps_previous_keys() {
+        let url = mock_jwks(vec![(200, KEYS_A), (500, "{}")]).await;
+        let (keys, metrics) = (KeyStore::default(), Arc::new(Metrics::new()));
+        let task = tokio::spawn(run_jwks_refresh(keys.clone(), Client::new(), metrics.clone(), url, None, Duration::from_millis(20)));
+
+        wait_until("failed refreshes", || metrics.jwks_refresh_count(false) >= 2).await;
+        assert!(has_key(&keys, "a"));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_key_set_is_rejected_without_clearing_keys() {
+        let url = mock_jwks(vec![(200, KEYS_A), (200, "not json"), (200, r#"{"keys":[]}"#)]).await;
+        let snapshot = env::temp_dir().join(format!("jwks-refresh-{}.json", std::process::id()));
+        let (keys, metrics) = (KeyStore::default(), Arc::new(Metrics::new()));
+        let refresh = run_jwks_refresh(keys.clone(), Client::new(), metrics.clone(), url, Some(snapshot.clone()), Duration::from_millis(20));
+        let task = tokio::spawn(refresh);
+
+        wait_until("rejected key sets", || metrics.jwks_refresh_count(false) >= 3).await;
+        task.abort();
+        assert!(has_key(&keys, "a"));
+        // Nor is the snapshot overwritten.
+        assert!(load_jwks_file(&snapshot).unwrap().find("a").is_some());
+        std::fs::remove_file(&snapshot).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_reload_swaps_valid_keys_only() {
+        let path = env::temp_dir().join(format!("jwks-watch-{}.json", std::process::id()));
+        // Bumps the mtime explicitly, as coarse filesystem timestamps may not change between writes.
+        let write = |contents: &str, mtime_offset: u64| {
+            std::fs::write(&path, contents).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(mtime_offset)).unwrap();
+        };
+        write(KEYS_A, 0);
+        let (keys, metrics) = (KeyStore::default(), Arc::new(Metrics::new()));
+        keys.install(load_jwks_file(&path).unwrap());
+        let task = tokio::spawn(watch_jwks_file(keys.clone(), metrics.clone(), path.clone(), Duration::from_millis(10)));
+        // Let the watcher record the starting mtime before the file changes.
+        tokio::task::yield_now().await;
+
+        write(KEYS_B, 1);
+        wait_until("rotated keys", || has_key(&keys, "b")).await;
+        assert!(!has_key(&keys, "a"));
+
+        write(r#"{"keys":[{"kty":"oct","kid":"c""#, 2);
+        wait_until("rejected reload", || metrics.jwks_refresh_count(false) == 1).await;
+        assert!(has_key(&keys, "b"));
+
+        task.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,62 +1,75 @@
 use axum::{
-    extract::Request,
+    extract::{Request, State},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     http::header,
 };
-use jsonwebtoken::{decode, decode_header, Validation, jwk::AlgorithmParameters, DecodingKey, Algorithm};
-use std::env;
+use jsonwebtoken::{decode, decode_header, Validation, jwk::AlgorithmParameters, Algorithm};
 use serde_json::Value;
+use std::sync::Arc;
 
-use super::jwks::get_jwks;
-use super::error::AuthError;
-use super::user_context::AuthUser;
+use crate::config::AppConfig;
+use crate::metrics::Metrics;
 
-async fn validate_token(token_str: &str) -> Result<Value, AuthError> { 
+use super::jwks::{decoding_key, JwksError, KeyStore};
+use super::error::{AuthError, AuthFailure};
+use super::user_context::{AuthUser, UserRole};
+
+async fn validate_token(token_str: &str, config: &AppConfig, keys: &KeyStore) -> Result<Value, AuthError> {
     let token_header = decode_header(token_str).map_err(|e| AuthError::InvalidToken(format!("Invalid token header: {}", e)))?;
     let kid = token_header.kid.ok_or_else(|| AuthError::InvalidToken("Token header missing 'kid' (key ID)".to_string()))?;
     let alg_from_header = token_header.alg;
     
-    let jwks = get_jwks().await.map_err(AuthError::JwksProcessingError)?;
+    let jwks = keys.get().map_err(|e| match e {
+        JwksError::NotLoaded => AuthError::KeysNotReady,
+        e => AuthError::JwksProcessingError(e),
+    })?;
     let jwk = jwks.find(&kid).ok_or_else(|| AuthError::JwkKidNotFound { kid: kid.clone() })?;
 
     // The `decode` function later will use the alg_from_header from Validation 
     // and the key material from jwk. It should internally handle algorithm compatibility.
 
-    let required_iss = env::var("SUPABASE_JWT_ISS")
-        .map_err(|_| AuthError::MissingEnvVar("SUPABASE_JWT_ISS".to_string()))?;
-    let required_aud = env::var("SUPABASE_JWT_AUD")
-        .map_err(|_| AuthError::MissingEnvVar("SUPABASE_JWT_AUD".to_string()))?;
-
     let mut validation = Validation::new(alg_from_header);
-    validation.set_issuer(&[required_iss]);
-    validation.set_audience(&[required_aud]);
+    validation.set_issuer(&[&config.auth.jwt_issuer]);
+    validation.set_audience(&[&config.auth.jwt_audience]);
 
-    let decoding_key = match &jwk.algorithm {
-        AlgorithmParameters::RSA(rsa_params) => {
-            if !matches!(alg_from_header, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512) {
-                return Err(AuthError::InvalidToken("JWK is RSA but token algorithm is not an RSA variant".to_string()));
-            }
-            DecodingKey::from_rsa_components(&rsa_params.n, &rsa_params.e)
-                .map_err(|e| AuthError::InvalidToken(format!("Failed to create RSA decoding key from JWK: {}", e)))?
-        }
-        AlgorithmParameters::OctetKey(oct_params) => { 
-            if !matches!(alg_from_header, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
-                return Err(AuthError::InvalidToken("JWK is OctetKey but token algorithm is not an HMAC variant".to_string()));
-            }
-            DecodingKey::from_secret(oct_params.value.as_ref())
-        }
-        // AI: Add EllipticCurve (EC) support if needed, with similar type checking
-        // AlgorithmParameters::EllipticCurve(ec_params) => { ... }
-        _ => return Err(AuthError::InvalidToken(format!("Unsupported JWK algorithm parameters for creating decoding key: {:?}", jwk.algorithm))),
+    let compatible = match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => matches!(alg_from_header, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512),
+        AlgorithmParameters::OctetKey(_) => matches!(alg_from_header, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512),
+        AlgorithmParameters::EllipticCurve(_) => matches!(alg_from_header, Algorithm::ES256 | Algorithm::ES384),
+        AlgorithmParameters::OctetKeyPair(_) => alg_from_header == Algorithm::EdDSA,
     };
+    if !compatible {
+        return Err(AuthError::InvalidToken(format!("JWK type does not match token algorithm {:?}", alg_from_header)));
+    }
+    let decoding_key = decoding_key(jwk)
+        .map_err(|e| AuthError::InvalidToken(format!("Failed to create decoding key from JWK: {}", e)))?;
 
     let token_data = decode::<Value>(token_str, &decoding_key, &validation)?;
     
     Ok(token_data.claims)
 }
 
-pub async fn jwt_auth_middleware(mut req: Request, next: Next) -> Result<Response, AuthError> {
+pub async fn jwt_auth_middleware(
+    State(config): State<Arc<AppConfig>>,
+    State(keys): State<KeyStore>,
+    State(metrics): State<Arc<Metrics>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let response = match authenticate(&mut req, &config, &keys).await {
+        Ok(()) => next.run(req).await,
+        Err(e) => e.into_response(),
+    };
+    // Covers both token rejections here and role checks in the handlers' extractors.
+    if let Some(AuthFailure(kind)) = response.extensions().get::<AuthFailure>() {
+        metrics.auth_failure(kind);
+    }
+    response
+}
+
+/// Validates the bearer token and inserts the resulting `AuthUser` into the request.
+async fn authenticate(req: &mut Request, config: &AppConfig, keys: &KeyStore) -> Result<(), AuthError> {
     let auth_header = req.headers()
         .get(header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok());
@@ -71,7 +84,7 @@ pub async fn jwt_auth_middleware(mut req: Request, next: Next) -> Result<Respons
         return Err(AuthError::MissingToken);
     };
 
-    let claims = validate_token(&token_str).await?;
+    let claims = validate_token(&token_str, config, keys).await?;
     
     // Extract user information from claims
     let user_id = claims["sub"]
@@ -84,22 +97,22 @@ pub async fn jwt_auth_middleware(mut req: Request, next: Next) -> Result<Respons
     
     let email = claims["email"].as_str().map(|s| s.to_string());
     
-    // Extract role from claims - supports both direct "role" field and app_metadata.role
-    let role_str = if let Some(role) = claims["role"].as_str() {
-        Some(role)
-    } else if let Some(app_metadata) = claims.get("app_metadata") {
-        app_metadata["role"].as_str()
-    } else {
-        None
-    };
+    // Extract role from claims. `user_role` is set by the custom access token hook from
+    // `public.user_roles`; `app_metadata.role` and a direct "role" field are fallbacks.
+    // Supabase always sets "role" to the Postgres role ("authenticated"), so it comes last.
+    let role_str = claims["user_role"].as_str()
+        .or_else(|| claims["app_metadata"]["role"].as_str())
+        .or_else(|| claims["role"].as_str());
     
     // Parse role from string or use default
-    let role = match role_str {
-        Some("premium") => super::user_context::UserRole::Premium,
-        Some("admin") => super::user_context::UserRole::Admin,
-        _ => super::user_context::UserRole::User, // Default role
-    };
+    let role = UserRole::from_claim(role_str);
     
+    let permissions = claims["permissions"]
+        .as_array()
+        .map(|values| values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let tenant_id = claims["tenant_id"].as_str().map(|s| s.to_string());
+
     // Get token timestamps
     let iat = claims["iat"]
         .as_i64()
@@ -120,11 +133,14 @@ pub async fn jwt_auth_middleware(mut req: Request, next: Next) -> Result<Respons
         id: user_id,
         email,
         role,
+        permissions,
+        tenant_id,
         iat,
         exp,
+        claims: Arc::new(claims),
     };
     
+    crate::telemetry::record_user(&auth_user);
     req.extensions_mut().insert(auth_user);
-
-    Ok(next.run(req).await)
+    Ok(())
 } 
\ No newline at end of file
@@ -1,4 +1,5 @@
 pub mod jwks;
 pub mod error;
+pub mod gotrue;
 pub mod middleware;
-pub mod user_context;
\ No newline at end of file
+pub mod user_context;
@@ -1,18 +1,33 @@
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fmt;
+use std::sync::Arc;
 
 use super::error::AuthError;
 
 /// Represents a user's role in the system
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum UserRole {
+    #[default]
     User,
     Premium,
     Admin,
 }
 
+impl UserRole {
+    /// Parses a role claim value, falling back to `User` for missing or unknown roles.
+    pub fn from_claim(value: Option<&str>) -> Self {
+        match value {
+            Some("premium") => UserRole::Premium,
+            Some("admin") => UserRole::Admin,
+            _ => UserRole::User,
+        }
+    }
+}
+
 impl fmt::Display for UserRole {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -23,12 +38,6 @@ impl fmt::Display for UserRole {
     }
 }
 
-impl Default for UserRole {
-    fn default() -> Self {
-        UserRole::User
-    }
-}
-
 /// Represents authenticated user information extracted from JWT claims
 #[derive(Debug, Clone, Serialize)]
 pub struct AuthUser {
@@ -38,10 +47,17 @@ pub struct AuthUser {
     pub email: Option<String>,
     /// The user's role
     pub role: UserRole,
+    /// Fine-grained permissions from the `permissions` claim (set by the custom access token hook)
+    pub permissions: Vec<String>,
+    /// The tenant the user belongs to, from the `tenant_id` claim
+    pub tenant_id: Option<String>,
     /// When the token was issued (Unix timestamp)
     pub iat: i64,
     /// When the token expires (Unix timestamp)
     pub exp: i64,
+    /// All verified claims, forwarded to Postgres so RLS policies see the same JWT as PostgREST
+    #[serde(skip)]
+    pub claims: Arc<Value>,
 }
 
 /// Custom extractor for getting the authenticated user from request extensions
@@ -60,4 +76,25 @@ where
             None => Err(AuthError::InternalError("User context not found. Is the auth middleware applied?".into())),
         }
     }
-} 
\ No newline at end of file
+} 
+
+/// Extractor that only succeeds for users with the `Admin` role.
+/// Use it in place of `AuthUser` to guard admin-only handlers.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthUser);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        if user.role != UserRole::Admin {
+            return Err(AuthError::Forbidden("Admin role required".into()));
+        }
+        Ok(AdminUser(user))
+    }
+}
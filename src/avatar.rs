@@ -0,0 +1,86 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, Rgb, RgbImage};
+use std::io::Cursor;
+
+/// Square thumbnail edge lengths in pixels, largest first. `avatar_url` points at the first.
+pub const THUMBNAIL_SIZES: [u32; 3] = [512, 256, 128];
+/// Upload content types accepted. Independently, `thumbnails` sniffs the content and
+/// requires one of these formats, whichever of them was declared.
+pub const ACCEPTED_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+const MAX_DIMENSION: u32 = 8192;
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AvatarError {
+    #[error("Unsupported image format; upload a JPEG, PNG or WebP image")]
+    UnsupportedFormat,
+
+    #[error("Image could not be decoded: {0}")]
+    Decode(#[from] image::ImageError),
+}
+
+/// Decodes an uploaded image and renders every `THUMBNAIL_SIZES` entry as a
+/// center-cropped JPEG. Re-encoding drops EXIF data (such as GPS position) after the
+/// orientation has been applied. CPU-bound: call it from `spawn_blocking`.
+pub fn thumbnails(upload: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, AvatarError> {
+    let mut reader = ImageReader::new(Cursor::new(upload)).with_guessed_format().map_err(image::ImageError::IoError)?;
+    if !matches!(reader.format(), Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)) {
+        return Err(AvatarError::UnsupportedFormat);
+    }
+    // Guards against decompression bombs: a small file can claim a huge canvas.
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let mut source = image;
+    let mut rendered = Vec::with_capacity(THUMBNAIL_SIZES.len());
+    for size in THUMBNAIL_SIZES {
+        // Each size is scaled down from the previous one, which is much cheaper than
+        // starting from the full-size upload every time.
+        source = source.resize_to_fill(size, size, FilterType::Lanczos3);
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode_image(&flatten(&source))?;
+        rendered.push((size, jpeg));
+    }
+    Ok(rendered)
+}
+
+/// Composites transparent pixels onto white, since JPEG has no alpha channel.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |channel: u8| ((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn test_renders_square_jpeg_thumbnails() {
+        let upload = ImageBuffer::from_pixel(900, 600, Rgba([10u8, 20, 30, 0]));
+        let mut png = Vec::new();
+        upload.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+
+        let rendered = thumbnails(&png).unwrap();
+        assert_eq!(rendered.iter().map(|(size, _)| *size).collect::<Vec<_>>(), THUMBNAIL_SIZES);
+        let smallest = image::load_from_memory_with_format(&rendered[2].1, ImageFormat::Jpeg).unwrap();
+        assert_eq!((smallest.width(), smallest.height()), (128, 128));
+        // Fully transparent input comes out white.
+        assert!(smallest.to_rgb8().get_pixel(64, 64).0.iter().all(|&channel| channel > 250));
+
+        assert!(matches!(thumbnails(b"GIF89a not really"), Err(AvatarError::UnsupportedFormat)));
+    }
+}
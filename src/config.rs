@@ -0,0 +1,998 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::rate_limit::RateLimitConfig;
+use crate::webhooks::signature::{decode_secret, parse_secret};
+
+/// Environment variable pointing at an optional TOML config file.
+pub const CONFIG_FILE_VAR: &str = "APP_CONFIG_FILE";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Could not read config file {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+
+    #[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+/// Application configuration, loaded once at startup.
+///
+/// Values are layered: built-in defaults, then the TOML file named by
+/// `APP_CONFIG_FILE` (if any), then environment variables, which always win.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub http: HttpConfig,
+    pub profiles: ProfileConfig,
+    pub storage: StorageConfig,
+    pub rate_limit: RateLimitConfig,
+    pub webhooks: WebhookConfig,
+    pub logging: LoggingConfig,
+    pub metrics: MetricsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Addresses to listen on. When empty, the server listens on `host:port` only.
+    pub binds: Vec<BindAddr>,
+    pub tls: TlsConfig,
+    /// Time between failing readiness and closing the listeners, so load balancers
+    /// stop routing new requests here first.
+    pub shutdown_delay_secs: u64,
+    /// How long in-flight requests may take to finish once listeners are closed.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3000,
+            binds: Vec::new(),
+            tls: TlsConfig::default(),
+            shutdown_delay_secs: 0,
+            // Leaves headroom within Kubernetes' default 30s termination grace period.
+            shutdown_timeout_secs: 25,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    /// The configured `binds`, or `host:port` if none are set.
+    pub fn bind_addrs(&self) -> Vec<BindAddr> {
+        if self.binds.is_empty() { vec![BindAddr::Tcp(self.addr())] } else { self.binds.clone() }
+    }
+
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay_secs)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
+
+/// A listening address: `0.0.0.0:3000`, `[::]:3000` or `unix:/run/app.sock`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for BindAddr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.strip_prefix("unix:") {
+            Some("") => Err("unix: bind needs a socket path".to_string()),
+            Some(path) => Ok(BindAddr::Unix(PathBuf::from(path))),
+            None => value
+                .parse()
+                .map(BindAddr::Tcp)
+                .map_err(|_| format!("'{}' is not an address like 0.0.0.0:3000, [::]:3000 or unix:/path", value)),
+        }
+    }
+}
+
+impl TryFrom<String> for BindAddr {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "{}", addr),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// TLS for the TCP listeners. Disabled unless both paths are set; the files are
+/// polled and reloaded when they change, so renewed certificates need no restart.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1).
+    pub key_path: Option<PathBuf>,
+    pub reload_interval_secs: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self { cert_path: None, key_path: None, reload_interval_secs: 60 }
+    }
+}
+
+impl TlsConfig {
+    pub fn enabled(&self) -> bool {
+        self.cert_path.is_some() && self.key_path.is_some()
+    }
+
+    pub fn reload_interval(&self) -> Duration {
+        Duration::from_secs(self.reload_interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            // Supabase limits direct connections; keep the pool small by default.
+            max_connections: 10,
+            min_connections: 2,
+            acquire_timeout_secs: 8,
+            idle_timeout_secs: 8 * 60 * 60,
+            max_lifetime_secs: 16 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Supabase JWKS endpoint, refreshed periodically.
+    pub jwks_url: Option<String>,
+    /// Static JWKS file, reloaded when it changes. Takes precedence over `jwks_url`.
+    pub jwks_file: Option<PathBuf>,
+    /// Inline JWKS JSON. Takes precedence over `jwks_file` and `jwks_url`.
+    pub jwks_json: Option<String>,
+    /// Last-known-good JWKS snapshot used at boot while the endpoint is unreachable.
+    pub jwks_cache_path: Option<PathBuf>,
+    pub jwks_refresh_interval_secs: u64,
+    pub jwks_file_poll_interval_secs: u64,
+    /// Required `iss` claim.
+    pub jwt_issuer: String,
+    /// Required `aud` claim.
+    pub jwt_audience: String,
+    /// Project URL, used with `service_role_key` for the auth admin API.
+    pub supabase_url: Option<String>,
+    pub service_role_key: Option<String>,
+    /// Standard Webhooks secret of the custom access token hook (`v1,whsec_...`).
+    pub hook_secret: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwks_url: None,
+            jwks_file: None,
+            jwks_json: None,
+            jwks_cache_path: None,
+            jwks_refresh_interval_secs: 60 * 60,
+            jwks_file_poll_interval_secs: 30,
+            jwt_issuer: String::new(),
+            jwt_audience: String::new(),
+            supabase_url: None,
+            service_role_key: None,
+            hook_secret: None,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn jwks_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.jwks_refresh_interval_secs)
+    }
+
+    pub fn jwks_file_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.jwks_file_poll_interval_secs)
+    }
+
+    /// Age after which fetched keys count as stale: three missed refreshes. File and
+    /// inline keys are only reloaded when they change, so they never go stale.
+    pub fn jwks_max_age(&self) -> Option<Duration> {
+        let fetched = self.jwks_json.is_none() && self.jwks_file.is_none() && self.jwks_url.is_some();
+        fetched.then(|| self.jwks_refresh_interval() * 3)
+    }
+
+    /// Decoded hook secret; the value is checked by `AppConfig::validate`.
+    pub fn hook_secret_bytes(&self) -> Option<Vec<u8>> {
+        self.hook_secret.as_deref().and_then(|secret| decode_secret(secret).ok())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API from a browser, or `*`. Empty disables CORS.
+    pub allowed_origins: Vec<String>,
+    /// Request headers browsers may send cross-origin.
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response.
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_headers: ["authorization", "content-type", "x-request-id"].map(String::from).to_vec(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+/// Limits and response headers applied to every route.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Largest request body accepted by the extractors; larger bodies get 413.
+    pub max_body_bytes: usize,
+    pub request_timeout_secs: u64,
+    /// Timeout overrides keyed by route prefix (e.g. `"/api/admin" = 60`); the longest
+    /// matching prefix wins.
+    pub route_timeouts: BTreeMap<String, u64>,
+    /// gzip/brotli response compression, negotiated with `Accept-Encoding`.
+    pub compression: bool,
+    /// `Strict-Transport-Security` max-age; 0 omits the header. Browsers ignore it
+    /// over plain HTTP, so it only takes effect behind TLS.
+    pub hsts_max_age_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024,
+            request_timeout_secs: 30,
+            route_timeouts: BTreeMap::new(),
+            compression: true,
+            hsts_max_age_secs: 365 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Timeout for a route template such as `/api/profiles/:user_id`.
+    pub fn timeout_for(&self, route: &str) -> Duration {
+        let secs = self
+            .route_timeouts
+            .iter()
+            .filter(|(prefix, _)| route.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.request_timeout_secs, |(_, secs)| *secs);
+        Duration::from_secs(secs)
+    }
+}
+
+/// Profile validation. See `validation::ProfileValidator`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// JSON Schema file that `metadata` must match. Without one, any object is accepted.
+    pub metadata_schema: Option<PathBuf>,
+    /// Largest serialized `metadata` object accepted.
+    pub metadata_max_bytes: usize,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        Self { metadata_schema: None, metadata_max_bytes: 16 * 1024 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Files under `storage.local_dir`, served by the API at `/uploads`.
+    #[default]
+    Local,
+    /// An S3-compatible bucket, such as Supabase Storage's S3 endpoint.
+    S3,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "local" => Ok(StorageBackend::Local),
+            "s3" => Ok(StorageBackend::S3),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Where uploaded files (avatars) are stored. See `storage::init`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Base URL the stored files are publicly reachable under. Required for `s3`;
+    /// for `local` it defaults to `/uploads` on the server's first TCP address.
+    pub public_url: Option<String>,
+    pub local_dir: PathBuf,
+    pub s3: S3Config,
+    /// Largest avatar upload accepted.
+    pub avatar_max_bytes: usize,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::default(),
+            public_url: None,
+            local_dir: PathBuf::from("uploads"),
+            s3: S3Config::default(),
+            avatar_max_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// `public_url` without a trailing slash; the value is checked by `AppConfig::validate`,
+    /// and `AppConfig::warnings` reports a fallback address clients cannot reach.
+    pub fn public_url(&self, server: &ServerConfig) -> String {
+        match &self.public_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => {
+                let addr = first_tcp_addr(server).unwrap_or_else(|| server.addr());
+                let scheme = if server.tls.enabled() { "https" } else { "http" };
+                format!("{}://{}/uploads", scheme, addr)
+            }
+        }
+    }
+}
+
+fn first_tcp_addr(server: &ServerConfig) -> Option<SocketAddr> {
+    server.bind_addrs().into_iter().find_map(|bind| match bind {
+        BindAddr::Tcp(addr) => Some(addr),
+        BindAddr::Unix(_) => None,
+    })
+}
+
+/// Path-style S3 access. For Supabase Storage, `endpoint` is
+/// `https://<project>.supabase.co/storage/v1/s3` and the keys come from the dashboard.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3Config {
+    pub endpoint: Option<String>,
+    pub region: String,
+    pub bucket: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Shared secret for inbound webhooks; the routes are only mounted when set.
+    pub secret: Option<String>,
+    /// Allowed clock drift of signed timestamps.
+    pub tolerance_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self { secret: None, tolerance_secs: 5 * 60 }
+    }
+}
+
+impl WebhookConfig {
+    pub fn tolerance(&self) -> Duration {
+        Duration::from_secs(self.tolerance_secs)
+    }
+
+    /// Parsed shared secret; the value is checked by `AppConfig::validate`.
+    pub fn secret_bytes(&self) -> Option<Vec<u8>> {
+        self.secret.as_deref().and_then(|secret| parse_secret(secret).ok())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable, for local development.
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log aggregation.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Level filter in `RUST_LOG` syntax, e.g. `info,supabase_axum=debug`.
+    pub filter: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self { format: LogFormat::default(), filter: "info,sqlx=warn".to_string() }
+    }
+}
+
+/// Prometheus `/metrics`, served on its own admin listener so it is never
+/// exposed next to the public API.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: true, host: IpAddr::V4(Ipv4Addr::LOCALHOST), port: 9090 }
+    }
+}
+
+impl MetricsConfig {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+impl AppConfig {
+    /// Loads the config file named by `APP_CONFIG_FILE` (if set), applies environment
+    /// overrides and validates the result. Every problem is reported at once.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match env::var_os(CONFIG_FILE_VAR) {
+            Some(path) => Self::from_file(Path::new(&path))?,
+            None => Self::default(),
+        };
+
+        let mut errors = config.apply_env(|name| env::var(name).ok());
+        errors.extend(config.validate());
+        if errors.is_empty() { Ok(config) } else { Err(ConfigError::Invalid(errors)) }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+    }
+
+    /// Overrides values with the environment variables that are set (and non-empty).
+    /// Returns the variables that could not be parsed.
+    fn apply_env(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut env = EnvOverrides { lookup, errors: Vec::new() };
+
+        env.parse("SERVER_HOST", &mut self.server.host);
+        // `PORT` is what most container platforms set; the explicit variable wins. Those
+        // platforms connect from outside the container, so unless a host is chosen, `PORT`
+        // also moves the default loopback host to every interface.
+        if let Some(port) = env.value("PORT") {
+            self.server.port = port;
+            if env.get("SERVER_HOST").is_none() && self.server.host == ServerConfig::default().host {
+                self.server.host = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+            }
+        }
+        env.parse("SERVER_PORT", &mut self.server.port);
+        env.list("SERVER_BINDS", &mut self.server.binds);
+        env.optional("TLS_CERT_PATH", &mut self.server.tls.cert_path);
+        env.optional("TLS_KEY_PATH", &mut self.server.tls.key_path);
+        env.parse("TLS_RELOAD_INTERVAL_SECS", &mut self.server.tls.reload_interval_secs);
+        env.parse("SHUTDOWN_DELAY_SECS", &mut self.server.shutdown_delay_secs);
+        env.parse("SHUTDOWN_TIMEOUT_SECS", &mut self.server.shutdown_timeout_secs);
+
+        env.parse("DATABASE_URL", &mut self.database.url);
+        env.parse("DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections);
+        env.parse("DATABASE_MIN_CONNECTIONS", &mut self.database.min_connections);
+        env.parse("DATABASE_ACQUIRE_TIMEOUT_SECS", &mut self.database.acquire_timeout_secs);
+        env.parse("DATABASE_IDLE_TIMEOUT_SECS", &mut self.database.idle_timeout_secs);
+        env.parse("DATABASE_MAX_LIFETIME_SECS", &mut self.database.max_lifetime_secs);
+
+        let auth = &mut self.auth;
+        env.optional("SUPABASE_JWKS_URL", &mut auth.jwks_url);
+        env.optional("SUPABASE_JWKS_FILE", &mut auth.jwks_file);
+        env.optional("SUPABASE_JWKS_JSON", &mut auth.jwks_json);
+        env.optional("SUPABASE_JWKS_CACHE_PATH", &mut auth.jwks_cache_path);
+        env.parse("JWKS_REFRESH_INTERVAL_SECS", &mut auth.jwks_refresh_interval_secs);
+        env.parse("JWKS_FILE_POLL_INTERVAL_SECS", &mut auth.jwks_file_poll_interval_secs);
+        env.parse("SUPABASE_JWT_ISS", &mut auth.jwt_issuer);
+        env.parse("SUPABASE_JWT_AUD", &mut auth.jwt_audience);
+        env.optional("SUPABASE_URL", &mut auth.supabase_url);
+        env.optional("SUPABASE_SERVICE_ROLE_KEY", &mut auth.service_role_key);
+        env.optional("SUPABASE_AUTH_HOOK_SECRET", &mut auth.hook_secret);
+
+        env.list("CORS_ALLOWED_ORIGINS", &mut self.cors.allowed_origins);
+        env.list("CORS_ALLOWED_HEADERS", &mut self.cors.allowed_headers);
+        env.parse("CORS_ALLOW_CREDENTIALS", &mut self.cors.allow_credentials);
+        env.parse("CORS_MAX_AGE_SECS", &mut self.cors.max_age_secs);
+
+        env.parse("HTTP_MAX_BODY_BYTES", &mut self.http.max_body_bytes);
+        env.parse("HTTP_REQUEST_TIMEOUT_SECS", &mut self.http.request_timeout_secs);
+        env.parse("HTTP_COMPRESSION", &mut self.http.compression);
+        env.parse("HSTS_MAX_AGE_SECS", &mut self.http.hsts_max_age_secs);
+
+        env.optional("PROFILE_METADATA_SCHEMA", &mut self.profiles.metadata_schema);
+        env.parse("PROFILE_METADATA_MAX_BYTES", &mut self.profiles.metadata_max_bytes);
+
+        let storage = &mut self.storage;
+        env.parse("STORAGE_BACKEND", &mut storage.backend);
+        env.optional("STORAGE_PUBLIC_URL", &mut storage.public_url);
+        env.parse("STORAGE_LOCAL_DIR", &mut storage.local_dir);
+        env.optional("S3_ENDPOINT", &mut storage.s3.endpoint);
+        env.parse("S3_REGION", &mut storage.s3.region);
+        env.optional("S3_BUCKET", &mut storage.s3.bucket);
+        env.optional("S3_ACCESS_KEY_ID", &mut storage.s3.access_key_id);
+        env.optional("S3_SECRET_ACCESS_KEY", &mut storage.s3.secret_access_key);
+        env.parse("AVATAR_MAX_BYTES", &mut storage.avatar_max_bytes);
+
+        let limits = &mut self.rate_limit;
+        env.parse("RATE_LIMIT_ENABLED", &mut limits.enabled);
+        env.parse("RATE_LIMIT_USER_PER_MINUTE", &mut limits.user.per_minute);
+        env.parse("RATE_LIMIT_PREMIUM_PER_MINUTE", &mut limits.premium.per_minute);
+        env.parse("RATE_LIMIT_ADMIN_PER_MINUTE", &mut limits.admin.per_minute);
+        env.parse("RATE_LIMIT_ANONYMOUS_PER_MINUTE", &mut limits.anonymous.per_minute);
+        env.parse("RATE_LIMIT_TRUST_PROXY", &mut limits.trust_proxy);
+        env.parse("RATE_LIMIT_TRUSTED_HOPS", &mut limits.trusted_hops);
+
+        env.optional("WEBHOOK_SECRET", &mut self.webhooks.secret);
+        env.parse("WEBHOOK_TOLERANCE_SECS", &mut self.webhooks.tolerance_secs);
+
+        env.parse("LOG_FORMAT", &mut self.logging.format);
+        env.parse("RUST_LOG", &mut self.logging.filter);
+
+        env.parse("METRICS_ENABLED", &mut self.metrics.enabled);
+        env.parse("METRICS_HOST", &mut self.metrics.host);
+        env.parse("METRICS_PORT", &mut self.metrics.port);
+
+        env.errors
+    }
+
+    /// Settings that work but are probably not what was meant. Logged at startup.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        // Without a public URL, local files are linked on the first TCP address, which is
+        // useless to clients when it is a wildcard or missing. Uploads still succeed.
+        if self.storage.backend == StorageBackend::Local && self.storage.public_url.is_none() {
+            match first_tcp_addr(&self.server) {
+                Some(addr) if addr.ip().is_unspecified() => warnings.push(format!(
+                    "storage.public_url (STORAGE_PUBLIC_URL) is not set, so avatar URLs point at {}, which clients cannot reach",
+                    addr
+                )),
+                None => warnings.push(
+                    "storage.public_url (STORAGE_PUBLIC_URL) is not set and the server only listens on Unix sockets, so avatar URLs cannot be reached".to_string(),
+                ),
+                Some(_) => {}
+            }
+        }
+        warnings
+    }
+
+    /// Checks the whole configuration and returns every problem found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let server = &self.server;
+        if server.port == 0 {
+            errors.push("server.port must not be 0".to_string());
+        }
+        let binds = server.bind_addrs();
+        for (i, bind) in binds.iter().enumerate() {
+            if binds[..i].contains(bind) {
+                errors.push(format!("server.binds (SERVER_BINDS) lists {} twice", bind));
+            }
+        }
+        if server.tls.cert_path.is_some() != server.tls.key_path.is_some() {
+            errors.push("server.tls.cert_path (TLS_CERT_PATH) and server.tls.key_path (TLS_KEY_PATH) must be set together".to_string());
+        }
+        if server.tls.reload_interval_secs == 0 {
+            errors.push("server.tls.reload_interval_secs must be at least 1".to_string());
+        }
+
+        let db = &self.database;
+        if db.url.is_empty() {
+            errors.push("database.url (DATABASE_URL) is required".to_string());
+        } else if !(db.url.starts_with("postgres://") || db.url.starts_with("postgresql://")) {
+            errors.push("database.url (DATABASE_URL) must be a postgres:// URL".to_string());
+        }
+        if db.max_connections == 0 {
+            errors.push("database.max_connections must be at least 1".to_string());
+        }
+        if db.min_connections > db.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) exceeds database.max_connections ({})",
+                db.min_connections, db.max_connections
+            ));
+        }
+        if db.acquire_timeout_secs == 0 {
+            errors.push("database.acquire_timeout_secs must be at least 1".to_string());
+        }
+
+        let auth = &self.auth;
+        if auth.jwks_url.is_none() && auth.jwks_file.is_none() && auth.jwks_json.is_none() {
+            errors.push(
+                "one of auth.jwks_url (SUPABASE_JWKS_URL), auth.jwks_file (SUPABASE_JWKS_FILE) or auth.jwks_json (SUPABASE_JWKS_JSON) is required"
+                    .to_string(),
+            );
+        }
+        if let Some(url) = &auth.jwks_url
+            && reqwest::Url::parse(url).is_err()
+        {
+            errors.push(format!("auth.jwks_url (SUPABASE_JWKS_URL) is not a valid URL: {}", url));
+        }
+        if auth.jwks_refresh_interval_secs == 0 || auth.jwks_file_poll_interval_secs == 0 {
+            errors.push("JWKS refresh and poll intervals must be at least 1 second".to_string());
+        }
+        if auth.jwt_issuer.is_empty() {
+            errors.push("auth.jwt_issuer (SUPABASE_JWT_ISS) is required".to_string());
+        }
+        if auth.jwt_audience.is_empty() {
+            errors.push("auth.jwt_audience (SUPABASE_JWT_AUD) is required".to_string());
+        }
+        match (&auth.supabase_url, &auth.service_role_key) {
+            (Some(url), Some(_)) if reqwest::Url::parse(url).is_err() => {
+                errors.push(format!("auth.supabase_url (SUPABASE_URL) is not a valid URL: {}", url));
+            }
+            (Some(_), None) | (None, Some(_)) => errors.push(
+                "auth.supabase_url (SUPABASE_URL) and auth.service_role_key (SUPABASE_SERVICE_ROLE_KEY) must be set together"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+        if let Some(secret) = &auth.hook_secret
+            && let Err(e) = decode_secret(secret)
+        {
+            errors.push(format!("auth.hook_secret (SUPABASE_AUTH_HOOK_SECRET): {}", e));
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin == "*" {
+                if self.cors.allow_credentials {
+                    errors.push("cors.allowed_origins cannot be '*' when cors.allow_credentials is true".to_string());
+                }
+            } else if !is_origin(origin) {
+                errors.push(format!("cors.allowed_origins: '{}' is not an origin like https://app.example.com", origin));
+            }
+        }
+
+        for header in &self.cors.allowed_headers {
+            if axum::http::HeaderName::try_from(header.as_str()).is_err() {
+                errors.push(format!("cors.allowed_headers: '{}' is not a valid header name", header));
+            }
+        }
+
+        let http = &self.http;
+        if http.max_body_bytes == 0 {
+            errors.push("http.max_body_bytes (HTTP_MAX_BODY_BYTES) must be at least 1".to_string());
+        }
+        if http.request_timeout_secs == 0 {
+            errors.push("http.request_timeout_secs (HTTP_REQUEST_TIMEOUT_SECS) must be at least 1".to_string());
+        }
+        for (prefix, secs) in &http.route_timeouts {
+            if !prefix.starts_with('/') || *secs == 0 {
+                errors.push(format!("http.route_timeouts: '{}' needs a path starting with '/' and at least 1 second", prefix));
+            }
+        }
+
+        // "{}" is the smallest metadata object.
+        if self.profiles.metadata_max_bytes < 2 {
+            errors.push("profiles.metadata_max_bytes (PROFILE_METADATA_MAX_BYTES) must be at least 2".to_string());
+        }
+
+        let storage = &self.storage;
+        if let Some(url) = &storage.public_url
+            && !reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+        {
+            errors.push(format!("storage.public_url (STORAGE_PUBLIC_URL) is not an http(s) URL: {}", url));
+        }
+        if storage.backend == StorageBackend::S3 {
+            let s3 = &storage.s3;
+            let required = [
+                ("storage.s3.endpoint (S3_ENDPOINT)", s3.endpoint.is_some()),
+                ("storage.s3.region (S3_REGION)", !s3.region.is_empty()),
+                ("storage.s3.bucket (S3_BUCKET)", s3.bucket.is_some()),
+                ("storage.s3.access_key_id (S3_ACCESS_KEY_ID)", s3.access_key_id.is_some()),
+                ("storage.s3.secret_access_key (S3_SECRET_ACCESS_KEY)", s3.secret_access_key.is_some()),
+                ("storage.public_url (STORAGE_PUBLIC_URL)", storage.public_url.is_some()),
+            ];
+            for (name, set) in required {
+                if !set {
+                    errors.push(format!("{} is required when storage.backend is s3", name));
+                }
+            }
+            if let Some(endpoint) = &s3.endpoint
+                && reqwest::Url::parse(endpoint).is_err()
+            {
+                errors.push(format!("storage.s3.endpoint (S3_ENDPOINT) is not a valid URL: {}", endpoint));
+            }
+        }
+        if storage.avatar_max_bytes == 0 {
+            errors.push("storage.avatar_max_bytes (AVATAR_MAX_BYTES) must be at least 1".to_string());
+        }
+
+        let limits = &self.rate_limit;
+        let tiers = [("user", limits.user), ("premium", limits.premium), ("admin", limits.admin), ("anonymous", limits.anonymous)];
+        for (name, tier) in tiers {
+            if tier.per_minute == 0 {
+                errors.push(format!("rate_limit.{} must be at least 1 request per minute", name));
+            }
+        }
+        if limits.trust_proxy && limits.trusted_hops == 0 {
+            errors.push("rate_limit.trusted_hops (RATE_LIMIT_TRUSTED_HOPS) must be at least 1".to_string());
+        }
+
+        if let Some(secret) = &self.webhooks.secret
+            && let Err(e) = parse_secret(secret)
+        {
+            errors.push(format!("webhooks.secret (WEBHOOK_SECRET): {}", e));
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.logging.filter) {
+            errors.push(format!("logging.filter (RUST_LOG): {}", e));
+        }
+
+        let api_ports: Vec<u16> = binds
+            .iter()
+            .filter_map(|bind| match bind {
+                BindAddr::Tcp(addr) => Some(addr.port()),
+                BindAddr::Unix(_) => None,
+            })
+            .collect();
+        if self.metrics.enabled && (self.metrics.port == 0 || api_ports.contains(&self.metrics.port)) {
+            errors.push(format!(
+                "metrics.port (METRICS_PORT) must be non-zero and differ from the API ports {:?}",
+                api_ports
+            ));
+        }
+
+        errors
+    }
+}
+
+/// `scheme://host[:port]` without path, query or trailing slash, as browsers send it.
+fn is_origin(value: &str) -> bool {
+    match reqwest::Url::parse(value) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url.host_str().is_some()
+                && url.origin().ascii_serialization() == value
+        }
+        Err(_) => false,
+    }
+}
+
+struct EnvOverrides<F> {
+    lookup: F,
+    errors: Vec<String>,
+}
+
+impl<F: Fn(&str) -> Option<String>> EnvOverrides<F> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.lookup)(name).filter(|value| !value.trim().is_empty())
+    }
+
+    fn value<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        let value = self.get(name)?;
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.errors.push(format!("{} has an invalid value: {}", name, value));
+                None
+            }
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str, target: &mut T) {
+        if let Some(value) = self.value(name) {
+            *target = value;
+        }
+    }
+
+    fn optional<T: FromStr>(&mut self, name: &str, target: &mut Option<T>) {
+        if let Some(value) = self.value(name) {
+            *target = Some(value);
+        }
+    }
+
+    fn list<T: FromStr>(&mut self, name: &str, target: &mut Vec<T>) {
+        let Some(value) = self.get(name) else {
+            return;
+        };
+        let mut parsed = Vec::new();
+        for item in value.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            match item.parse() {
+                Ok(item) => parsed.push(item),
+                Err(_) => {
+                    self.errors.push(format!("{} has an invalid value: {}", name, item));
+                    return;
+                }
+            }
+        }
+        *target = parsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let errors = AppConfig::default().validate();
+        for expected in ["DATABASE_URL", "SUPABASE_JWKS_URL", "SUPABASE_JWT_ISS", "SUPABASE_JWT_AUD"] {
+            assert!(errors.iter().any(|e| e.contains(expected)), "missing error for {}: {:?}", expected, errors);
+        }
+    }
+
+    #[test]
+    fn test_local_storage_warns_without_a_public_url_on_wildcard_binds() {
+        let mut config = AppConfig::default();
+        assert!(config.warnings().is_empty());
+        assert_eq!(config.storage.public_url(&config.server), "http://127.0.0.1:3000/uploads");
+
+        // The service still starts: a wildcard bind is what containers use.
+        config.server.binds = vec![BindAddr::Tcp("0.0.0.0:3000".parse().unwrap())];
+        assert!(!config.validate().iter().any(|e| e.contains("STORAGE_PUBLIC_URL")));
+        assert_eq!(
+            config.warnings(),
+            vec!["storage.public_url (STORAGE_PUBLIC_URL) is not set, so avatar URLs point at 0.0.0.0:3000, which clients cannot reach".to_string()]
+        );
+        config.server.binds = vec![BindAddr::Unix(PathBuf::from("/run/app.sock"))];
+        assert_eq!(config.warnings().len(), 1);
+
+        config.storage.public_url = Some("https://cdn.example.com/uploads/".to_string());
+        assert!(config.warnings().is_empty());
+        assert_eq!(config.storage.public_url(&config.server), "https://cdn.example.com/uploads");
+    }
+
+    #[test]
+    fn test_bind_addresses() {
+        let mut config = AppConfig::default();
+        let env: HashMap<&str, &str> = HashMap::from([("PORT", "8080")]);
+        assert!(config.apply_env(|name| env.get(name).map(|v| v.to_string())).is_empty());
+        assert_eq!(config.server.bind_addrs(), vec![BindAddr::Tcp("0.0.0.0:8080".parse().unwrap())]);
+
+        // An explicit host, from the environment or the config file, is kept.
+        let mut config = AppConfig::default();
+        let env: HashMap<&str, &str> = HashMap::from([("PORT", "8080"), ("SERVER_HOST", "127.0.0.1")]);
+        assert!(config.apply_env(|name| env.get(name).map(|v| v.to_string())).is_empty());
+        assert_eq!(config.server.bind_addrs(), vec![BindAddr::Tcp("127.0.0.1:8080".parse().unwrap())]);
+        config.server.host = "10.0.0.5".parse().unwrap();
+        let env: HashMap<&str, &str> = HashMap::from([("PORT", "8081")]);
+        assert!(config.apply_env(|name| env.get(name).map(|v| v.to_string())).is_empty());
+        assert_eq!(config.server.addr(), "10.0.0.5:8081".parse().unwrap());
+
+        let env: HashMap<&str, &str> = HashMap::from([("SERVER_BINDS", "0.0.0.0:3000, [::]:3000,unix:/run/app.sock")]);
+        assert!(config.apply_env(|name| env.get(name).map(|v| v.to_string())).is_empty());
+        assert_eq!(
+            config.server.bind_addrs(),
+            vec![
+                BindAddr::Tcp("0.0.0.0:3000".parse().unwrap()),
+                BindAddr::Tcp("[::]:3000".parse().unwrap()),
+                BindAddr::Unix(PathBuf::from("/run/app.sock")),
+            ]
+        );
+
+        let env: HashMap<&str, &str> = HashMap::from([("SERVER_BINDS", "localhost:3000")]);
+        let errors = config.apply_env(|name| env.get(name).map(|v| v.to_string()));
+        assert_eq!(errors, vec!["SERVER_BINDS has an invalid value: localhost:3000".to_string()]);
+    }
+
+    #[test]
+    fn test_environment_overrides_file() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [server]
+            host = "0.0.0.0"
+            port = 8080
+
+            [database]
+            url = "postgres://file"
+
+            [auth]
+            jwks_url = "https://example.supabase.co/auth/v1/.well-known/jwks.json"
+            jwt_issuer = "https://example.supabase.co/auth/v1"
+            jwt_audience = "authenticated"
+
+            [cors]
+            allowed_origins = ["https://app.example.com"]
+
+            [rate_limit]
+            user = 100
+
+            [storage]
+            public_url = "https://api.example.com/uploads"
+            "#,
+        )
+        .unwrap();
+
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("SERVER_PORT", "9000"),
+            ("DATABASE_URL", "postgres://env"),
+            ("DATABASE_MAX_CONNECTIONS", "many"),
+            ("SUPABASE_JWT_AUD", ""),
+        ]);
+        let errors = config.apply_env(|name| env.get(name).map(|v| v.to_string()));
+
+        assert_eq!(errors, vec!["DATABASE_MAX_CONNECTIONS has an invalid value: many".to_string()]);
+        assert_eq!(config.server.addr(), "0.0.0.0:9000".parse().unwrap());
+        assert_eq!(config.database.url, "postgres://env");
+        // Empty variables do not override the file.
+        assert_eq!(config.auth.jwt_audience, "authenticated");
+        assert_eq!(config.rate_limit.user.per_minute, 100);
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_example_file_is_valid() {
+        let config: AppConfig = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_cors_origins_must_be_bare_origins() {
+        let mut config = AppConfig::default();
+        config.cors.allowed_origins = vec!["https://app.example.com/".to_string(), "*".to_string()];
+        config.cors.allow_credentials = true;
+        let errors: Vec<_> = config.validate().into_iter().filter(|e| e.starts_with("cors")).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+    }
+}
@@ -1,16 +1,19 @@
 use sqlx::{postgres::PgPoolOptions, PgPool, Error as SqlxError};
-use std::env;
-use std::time::Duration;
+use std::future::Future;
+use std::time::{Duration, Instant};
 
+use crate::config::DatabaseConfig;
+
+pub mod audit_repository;
 pub mod models; // AI: Added models submodule
 pub mod profile_repository; // AI: Added profile_repository submodule
+pub mod rls;
+pub mod tx;
+pub mod user_role_repository;
 
 // AI: Consider moving this error to a more general AppError enum in Phase 4.1
 #[derive(Debug, thiserror::Error)]
 pub enum DbError {
-    #[error("DATABASE_URL not set in environment")]
-    DatabaseUrlNotSet,
-
     #[error("Failed to create database pool: {0}")]
     PoolCreationFailed(SqlxError),
     
@@ -32,32 +35,135 @@ pub enum DbError {
 
     #[error("Failed to delete profile: {0}")]
     ProfileDeleteError(SqlxError),
+
+    #[error("Request transaction unavailable: {0}")]
+    TransactionUnavailable(&'static str),
 }
 
 // AI: This function initializes a PgPool. It should be called once at application startup.
 // The returned pool should be stored in Axum's application state.
-pub async fn init_db_pool() -> Result<PgPool, DbError> {
-    let database_url = env::var("DATABASE_URL").map_err(|_| DbError::DatabaseUrlNotSet)?;
-
-    // AI: Configure pool options as needed. These are some sensible defaults.
+pub async fn init_db_pool(config: &DatabaseConfig) -> Result<PgPool, DbError> {
+    // AI: Configure pool options as needed.
     // For Supabase, direct connections might be limited, so adjust max_connections accordingly.
     // Supabase uses PgBouncer, which might manage its own pool. Check Supabase best practices.
     PgPoolOptions::new()
-        .max_connections(10) // Max number of connections in the pool
-        .min_connections(2)  // Min number of connections to keep alive
-        .acquire_timeout(Duration::from_secs(8)) // Timeout for acquiring a connection
-        .idle_timeout(Duration::from_secs(8 * 60 * 60)) // Timeout for an idle connection (8 hours)
-        .max_lifetime(Duration::from_secs(16 * 60 * 60)) // Max lifetime of a connection (16 hours)
-        .connect(&database_url)
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+        .max_lifetime(Duration::from_secs(config.max_lifetime_secs))
+        .connect(&config.url)
         .await
         .map_err(DbError::PoolCreationFailed)
 }
 
-// Optional: A function to test the connection if needed during startup.
-// pub async fn test_db_connection(pool: &PgPool) -> Result<(), SqlxError> {
-//     sqlx::query("SELECT 1").fetch_one(pool).await?;
-//     Ok(())
-// }
+/// Runs a trivial query to verify the pool can actually reach the database.
+pub async fn test_db_connection(pool: &PgPool) -> Result<(), DbError> {
+    sqlx::query("SELECT 1")
+        .fetch_one(pool)
+        .await
+        .map_err(DbError::ConnectionError)?;
+    Ok(())
+}
+
+/// Tables and indexes from `schema.sql` the service relies on. Indexes are listed when
+/// behavior depends on them: the unique username index maps to `409 already_taken`, and
+/// search is unusably slow without its indexes. Keep these lists in sync with the schema.
+pub const REQUIRED_RELATIONS: &[&str] = &[
+    "public.profiles",
+    "public.user_roles",
+    "public.profile_audit_log",
+    "public.profiles_username_lower_key",
+    "public.profiles_search_vector_idx",
+    "public.profiles_search_trgm_idx",
+];
+
+/// Columns the queries use, including those added to existing databases by the
+/// `ALTER TABLE` upgrades in `schema.sql`.
+pub const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    (
+        "public.profiles",
+        &[
+            "id", "email", "username", "full_name", "avatar_url", "bio", "locale", "timezone", "metadata", "created_at",
+            "updated_at", "search_vector",
+        ],
+    ),
+    ("public.user_roles", &["user_id", "role", "permissions", "tenant_id"]),
+    ("public.profile_audit_log", &["actor_id", "profile_id", "action", "changes", "created_at"]),
+];
+
+pub const REQUIRED_EXTENSIONS: &[&str] = &["pg_trgm"];
+
+/// Returns the required relations, columns (`table.column`) and extensions
+/// (`extension name`) missing from the database. Columns of a missing table are not listed.
+pub async fn missing_schema_objects(pool: &PgPool) -> Result<Vec<String>, DbError> {
+    let (tables, columns): (Vec<&str>, Vec<&str>) = REQUIRED_COLUMNS
+        .iter()
+        .flat_map(|(table, columns)| columns.iter().map(move |column| (*table, *column)))
+        .unzip();
+    let missing = sqlx::query_scalar(
+        "SELECT name FROM unnest($1::text[]) AS name WHERE to_regclass(name) IS NULL
+        UNION ALL
+        SELECT table_name || '.' || column_name
+        FROM unnest($2::text[], $3::text[]) AS required(table_name, column_name)
+        WHERE to_regclass(table_name) IS NOT NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM pg_attribute
+                WHERE attrelid = to_regclass(table_name) AND attname = column_name AND NOT attisdropped
+            )
+        UNION ALL
+        SELECT 'extension ' || name FROM unnest($4::text[]) AS name
+        WHERE NOT EXISTS (SELECT 1 FROM pg_extension WHERE extname = name)"
+    )
+    .bind(REQUIRED_RELATIONS)
+    .bind(tables)
+    .bind(columns)
+    .bind(REQUIRED_EXTENSIONS)
+    .fetch_all(pool)
+    .await?;
+    Ok(missing)
+}
+
+/// Awaits a query and logs how long it took, inside the caller's span.
+pub(crate) async fn timed<T>(query: impl Future<Output = Result<T, SqlxError>>) -> Result<T, SqlxError> {
+    let started = Instant::now();
+    let result = query.await;
+    let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    match &result {
+        Ok(_) => tracing::debug!(elapsed_ms, "query finished"),
+        Err(e) => tracing::debug!(elapsed_ms, error = %e, "query failed"),
+    }
+    result
+}
 
 // AI: Placeholder for where to put database models (e.g., User struct for Phase 3.2)
-// pub mod models { ... } 
\ No newline at end of file
+// pub mod models { ... }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_pool;
+
+    const SCHEMA: &str = include_str!("db/schema.sql");
+
+    #[test]
+    fn test_required_objects_are_created_by_schema_sql() {
+        for relation in REQUIRED_RELATIONS {
+            let name = relation.trim_start_matches("public.");
+            assert!(
+                SCHEMA.contains(&format!("TABLE IF NOT EXISTS {}", relation)) || SCHEMA.contains(&format!("INDEX IF NOT EXISTS {} ", name)),
+                "{} is not created in schema.sql",
+                relation
+            );
+        }
+        for extension in REQUIRED_EXTENSIONS {
+            assert!(SCHEMA.contains(&format!("CREATE EXTENSION IF NOT EXISTS {};", extension)), "{}", extension);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schema_sql_has_every_required_object() {
+        let Some(pool) = test_pool().await else { return };
+        assert_eq!(missing_schema_objects(&pool).await.unwrap(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,30 @@
+use serde_json::Value;
+use sqlx::{query, PgExecutor};
+use uuid::Uuid;
+
+use super::models::ProfileAuditAction;
+use super::{timed, DbError};
+
+/// Records that `actor_id` (an admin) changed `profile_id`. Call it in the same
+/// transaction as the change, so the entry exists exactly when the change does.
+#[tracing::instrument(level = "debug", skip_all, fields(%actor_id, %profile_id))]
+pub async fn record_profile_change(
+    executor: impl PgExecutor<'_>,
+    actor_id: Uuid,
+    profile_id: Uuid,
+    action: ProfileAuditAction,
+    changes: &Value,
+) -> Result<(), DbError> {
+    timed(query(
+        "INSERT INTO public.profile_audit_log (actor_id, profile_id, action, changes)
+        VALUES ($1, $2, $3, $4)"
+    )
+    .bind(actor_id)
+    .bind(profile_id)
+    .bind(action.as_str())
+    .bind(changes)
+    .execute(executor))
+    .await?;
+
+    Ok(())
+}
@@ -1,37 +1,320 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::user_context::UserRole;
 
 // AI: This struct represents a user's profile in the database.
 // It's distinct from auth::user_context::AuthUser, which represents the authenticated JWT claims.
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
 pub struct UserProfile {
     pub id: Uuid, // Links to auth.users.id
     pub email: Option<String>,
     pub username: Option<String>,
-    // AI: Add other profile fields as needed, e.g., full_name, avatar_url
+    pub full_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    /// BCP 47 language tag, e.g. `en-US`.
+    pub locale: Option<String>,
+    /// IANA time zone, e.g. `Europe/Berlin`.
+    pub timezone: Option<String>,
+    /// Free-form application data, checked against `profiles.metadata_schema` when configured.
+    #[schema(value_type = Object)]
+    pub metadata: Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// The part of a profile any signed-in user may see, e.g. in search results.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct PublicProfile {
+    pub id: Uuid,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+}
+
+impl From<UserProfile> for PublicProfile {
+    fn from(profile: UserProfile) -> Self {
+        PublicProfile {
+            id: profile.id,
+            username: profile.username,
+            full_name: profile.full_name,
+            avatar_url: profile.avatar_url,
+            bio: profile.bio,
+        }
+    }
+}
+
 // AI: Payload for creating a new user profile. 
 // `id` and `email` might come from AuthUser or be explicitly set if creating for another user (admin scenarios).
 // For typical self-service, `id` and initial `email` would come from AuthUser context.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct CreateProfilePayload {
     // pub email: Option<String>, // Email might be pre-filled from AuthUser
     pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    /// Defaults to `{}`.
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
     // AI: Add other fields that can be set at creation
 }
 
 // AI: Payload for updating an existing user profile.
 // User can typically update fields like username, etc. Email updates might have special handling.
-#[derive(Debug, Deserialize)]
+/// Full replacement for `PUT`: every field must be present, `null` clears it
+/// (`metadata` is reset to `{}`).
+// `Option::deserialize` makes each field required, so `{}` is rejected instead of clearing everything.
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateProfilePayload {
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
     pub username: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
+    pub full_name: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
+    pub avatar_url: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
+    pub bio: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
+    pub locale: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true)]
+    pub timezone: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    #[schema(required = true, value_type = Option<Object>)]
+    pub metadata: Option<Value>,
     // AI: Add other updatable fields
 }
 
+/// One field of a JSON Merge Patch (RFC 7396): left out, `null`, or a new value.
+/// Fields must be marked `#[serde(default)]` so a missing key stays `Absent`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Patch<T> {
+    #[default]
+    Absent,
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    pub fn is_absent(&self) -> bool {
+        matches!(self, Patch::Absent)
+    }
+
+    /// The column's new value, or `None` when the field is left untouched.
+    pub fn update(&self) -> Option<Option<&T>> {
+        match self {
+            Patch::Absent => None,
+            Patch::Null => Some(None),
+            Patch::Value(value) => Some(Some(value)),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    // Only called for keys that are present; `#[serde(default)]` covers the rest.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Value(value),
+            None => Patch::Null,
+        })
+    }
+}
+
+/// Merge patch for `PATCH /profiles/me`: absent fields are kept, `null` clears them.
+/// `metadata` is merged key by key (see `merge_patch`); `null` resets it to `{}`.
+/// Unknown fields are rejected, so a misspelt field is not taken for an empty patch.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfilePatch {
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub username: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub full_name: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub avatar_url: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub bio: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub locale: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub timezone: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Patch<Value>,
+}
+
+impl ProfilePatch {
+    pub fn is_empty(&self) -> bool {
+        self.username.is_absent()
+            && self.full_name.is_absent()
+            && self.avatar_url.is_absent()
+            && self.bio.is_absent()
+            && self.locale.is_absent()
+            && self.timezone.is_absent()
+            && self.metadata.is_absent()
+    }
+}
+
+/// Applies an RFC 7396 merge patch to `target`: objects are merged recursively,
+/// `null` removes a key, and anything else replaces the value.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(changes) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(fields) = target else { unreachable!() };
+    for (key, value) in changes {
+        if value.is_null() {
+            fields.remove(key);
+        } else {
+            merge_patch(fields.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Filters for the admin profile listing. All given filters must match.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ProfileFilter {
+    /// Case-insensitive substring of the email.
+    pub email: Option<String>,
+    /// Case-insensitive substring of the username.
+    pub username: Option<String>,
+    /// Only profiles created at or after this time (RFC 3339).
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only profiles created before this time (RFC 3339).
+    pub created_before: Option<DateTime<Utc>>,
+    /// Role from `public.user_roles`; users without a row there count as `user`.
+    pub role: Option<UserRole>,
+}
+
+/// An admin write to someone else's profile, recorded in `public.profile_audit_log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileAuditAction {
+    Update,
+    Patch,
+    Delete,
+}
+
+impl ProfileAuditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProfileAuditAction::Update => "update",
+            ProfileAuditAction::Patch => "patch",
+            ProfileAuditAction::Delete => "delete",
+        }
+    }
+}
+
+/// The fields that differ between two versions of a profile, as
+/// `{"field": {"old": ..., "new": ...}}`. `after` is `None` for a deleted profile, so
+/// every set field is listed with a `null` new value. `updated_at` is left out.
+pub fn profile_changes(before: &UserProfile, after: Option<&UserProfile>) -> Value {
+    let to_map = |profile: &UserProfile| match serde_json::to_value(profile) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    let before = to_map(before);
+    let after = after.map(to_map).unwrap_or_default();
+    let changes = before
+        .into_iter()
+        .filter(|(field, _)| field != "updated_at")
+        .filter_map(|(field, old)| {
+            let new = after.get(&field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| (field, serde_json::json!({ "old": old, "new": new })))
+        })
+        .collect();
+    Value::Object(changes)
+}
+
+/// Role, permissions and tenant stored for a user in `public.user_roles`.
+/// This is the source of truth for the role claims added by the custom access token hook.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct UserRoleRecord {
+    pub user_id: Uuid,
+    pub role: String,
+    pub permissions: Vec<String>,
+    pub tenant_id: Option<Uuid>,
+}
+
 // AI: Response structure when returning a profile, could be UserProfile itself or a wrapper.
-// Using UserProfile directly for simplicity for now. 
\ No newline at end of file
+// Using UserProfile directly for simplicity for now. 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_patch_distinguishes_absent_and_null() {
+        let patch: ProfilePatch = serde_json::from_str("{}").unwrap();
+        assert!(patch.is_empty());
+        let patch: ProfilePatch = serde_json::from_str(r#"{"username": null}"#).unwrap();
+        assert_eq!(patch.username, Patch::Null);
+        let patch: ProfilePatch = serde_json::from_str(r#"{"username": "ada"}"#).unwrap();
+        assert_eq!(patch.username.update(), Some(Some(&"ada".to_string())));
+        let err = serde_json::from_str::<ProfilePatch>(r#"{"usrname": "ada"}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `usrname`"), "{}", err);
+
+        assert!(serde_json::from_str::<UpdateProfilePayload>("{}").is_err());
+        let replacement: UpdateProfilePayload = serde_json::from_str(
+            r#"{"username": null, "full_name": null, "avatar_url": null, "bio": null, "locale": null, "timezone": null, "metadata": null}"#,
+        )
+        .unwrap();
+        assert_eq!(replacement.username, None);
+    }
+
+    #[test]
+    fn test_merge_patch_follows_rfc_7396() {
+        let mut metadata = serde_json::json!({ "theme": "dark", "notifications": { "email": true, "sms": true } });
+        merge_patch(&mut metadata, &serde_json::json!({ "theme": null, "notifications": { "sms": false }, "beta": true }));
+        assert_eq!(metadata, serde_json::json!({ "notifications": { "email": true, "sms": false }, "beta": true }));
+
+        merge_patch(&mut metadata, &serde_json::json!(["replaced"]));
+        assert_eq!(metadata, serde_json::json!(["replaced"]));
+    }
+
+    #[test]
+    fn test_profile_changes_lists_changed_fields() {
+        let before: UserProfile = serde_json::from_value(serde_json::json!({
+            "id": Uuid::nil(), "email": "ada@example.com", "username": "ada", "full_name": null, "avatar_url": null,
+            "bio": null, "locale": null, "timezone": null, "metadata": {},
+            "created_at": "2026-01-01T00:00:00Z", "updated_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        let after = UserProfile {
+            username: None,
+            bio: Some("Hi".to_string()),
+            updated_at: Utc::now(),
+            ..before.clone()
+        };
+        assert_eq!(
+            profile_changes(&before, Some(&after)),
+            serde_json::json!({ "username": { "old": "ada", "new": null }, "bio": { "old": null, "new": "Hi" } })
+        );
+        let deleted = profile_changes(&before, None);
+        assert_eq!(deleted["email"], serde_json::json!({ "old": "ada@example.com", "new": null }));
+        assert!(deleted.get("bio").is_none());
+    }
+}
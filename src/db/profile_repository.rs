@@ -1,80 +1,333 @@
-use sqlx::{PgPool, query_as, query};
+use serde_json::Value;
+use sqlx::{FromRow, PgExecutor, PgPool, Postgres, QueryBuilder, query_as, query, query_scalar};
 use uuid::Uuid;
 
-use super::models::{UserProfile, CreateProfilePayload, UpdateProfilePayload};
-use super::DbError;
+use super::models::{UserProfile, CreateProfilePayload, ProfileFilter, ProfilePatch, UpdateProfilePayload};
+use super::{timed, DbError};
+use crate::listing::{self, ListQuery, Page, Sort, SortKey};
 
 // AI: Repository for UserProfile CRUD operations
+// Functions take any executor: the pool for privileged access, or a request
+// transaction (`db::tx::Tx`) to run atomically under the caller's RLS policies.
+
+/// Columns selected into `UserProfile`.
+const PROFILE_COLUMNS: &str =
+    "id, email, username, full_name, avatar_url, bio, locale, timezone, metadata, created_at, updated_at";
 
 /// Creates a new user profile.
 /// Assumes `id` and `email` are provided, typically derived from `AuthUser`.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
 pub async fn create_profile(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     user_id: Uuid,
     email: Option<String>,
     payload: CreateProfilePayload,
 ) -> Result<UserProfile, DbError> {
-    let profile = query_as::<_, UserProfile>(
-        "INSERT INTO public.profiles (id, email, username) 
-        VALUES ($1, $2, $3)
-        RETURNING id, email, username, created_at, updated_at"
-    )
+    let profile = timed(query_as::<_, UserProfile>(&format!(
+        "INSERT INTO public.profiles (id, email, username, full_name, avatar_url, bio, locale, timezone, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, '{{}}'::jsonb))
+        RETURNING {PROFILE_COLUMNS}"
+    ))
     .bind(user_id)
     .bind(email)
     .bind(&payload.username)
-    .fetch_one(pool)
+    .bind(&payload.full_name)
+    .bind(&payload.avatar_url)
+    .bind(&payload.bio)
+    .bind(&payload.locale)
+    .bind(&payload.timezone)
+    .bind(&payload.metadata)
+    .fetch_one(executor))
     .await
     .map_err(DbError::ProfileCreationError)?;
     
     Ok(profile)
 }
 
+/// Creates an empty profile for a user unless one already exists.
+/// Used when reacting to sign-ups, where the handler may see the same user twice.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn ensure_profile(executor: impl PgExecutor<'_>, user_id: Uuid, email: Option<String>) -> Result<(), DbError> {
+    timed(query(
+        "INSERT INTO public.profiles (id, email)
+        VALUES ($1, $2)
+        ON CONFLICT (id) DO NOTHING"
+    )
+    .bind(user_id)
+    .bind(email)
+    .execute(executor))
+    .await
+    .map_err(DbError::ProfileCreationError)?;
+
+    Ok(())
+}
+
 /// Fetches a user profile by its ID.
-pub async fn get_profile_by_id(pool: &PgPool, user_id: Uuid) -> Result<UserProfile, DbError> {
-    query_as::<_, UserProfile>(
-        "SELECT id, email, username, created_at, updated_at 
-        FROM public.profiles 
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn get_profile_by_id(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<UserProfile, DbError> {
+    timed(query_as::<_, UserProfile>(&format!(
+        "SELECT {PROFILE_COLUMNS}
+        FROM public.profiles
         WHERE id = $1"
-    )
+    ))
     .bind(user_id)
-    .fetch_optional(pool)
+    .fetch_optional(executor))
     .await?
     .ok_or(DbError::ProfileNotFound)
 }
 
-/// Updates an existing user profile.
+/// Fetches a profile and locks it until the transaction ends, e.g. to record what a
+/// following update changed.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn lock_profile(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<UserProfile, DbError> {
+    timed(query_as::<_, UserProfile>(&format!(
+        "SELECT {PROFILE_COLUMNS}
+        FROM public.profiles
+        WHERE id = $1
+        FOR UPDATE"
+    ))
+    .bind(user_id)
+    .fetch_optional(executor))
+    .await?
+    .ok_or(DbError::ProfileNotFound)
+}
+
+/// Replaces every editable field of an existing user profile (`PUT`).
+/// Partial updates go through `patch_profile`.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
 pub async fn update_profile(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     user_id: Uuid,
     payload: UpdateProfilePayload,
 ) -> Result<UserProfile, DbError> {
-    // AI: This implementation only updates username. Extend to update other fields as needed.
-    // Consider how to handle partial updates (e.g., only update fields that are Some(value)).
-    // For more complex partial updates, a query builder or dynamic query string might be needed,
-    // or multiple specific update functions.
-    
-    let updated_profile = query_as::<_, UserProfile>(
+    let updated_profile = timed(query_as::<_, UserProfile>(&format!(
         "UPDATE public.profiles
-        SET username = $2, updated_at = now()
+        SET username = $2, full_name = $3, avatar_url = $4, bio = $5, locale = $6, timezone = $7,
+            metadata = COALESCE($8, '{{}}'::jsonb), updated_at = now()
         WHERE id = $1
-        RETURNING id, email, username, created_at, updated_at"
-    )
+        RETURNING {PROFILE_COLUMNS}"
+    ))
     .bind(user_id)
     .bind(&payload.username)
-    .fetch_optional(pool) // Use fetch_optional in case the ID doesn't exist
-    .await?
+    .bind(&payload.full_name)
+    .bind(&payload.avatar_url)
+    .bind(&payload.bio)
+    .bind(&payload.locale)
+    .bind(&payload.timezone)
+    .bind(&payload.metadata)
+    .fetch_optional(executor)) // Use fetch_optional in case the ID doesn't exist
+    .await
+    .map_err(DbError::ProfileUpdateError)?
     .ok_or(DbError::ProfileNotFound)?; // Return ProfileNotFound if update affected 0 rows for the given ID
     
     Ok(updated_profile)
 }
 
+/// Applies a merge patch: only the fields present in `patch` are written.
+/// An empty patch changes nothing and returns the current profile.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn patch_profile(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    patch: &ProfilePatch,
+) -> Result<UserProfile, DbError> {
+    if patch.is_empty() {
+        return get_profile_by_id(executor, user_id).await;
+    }
+
+    // Column names are fixed here; only values are bound.
+    let mut builder = QueryBuilder::<Postgres>::new("UPDATE public.profiles SET updated_at = now()");
+    let text_columns = [
+        ("username", &patch.username),
+        ("full_name", &patch.full_name),
+        ("avatar_url", &patch.avatar_url),
+        ("bio", &patch.bio),
+        ("locale", &patch.locale),
+        ("timezone", &patch.timezone),
+    ];
+    for (column, value) in text_columns {
+        if let Some(value) = value.update() {
+            builder.push(format_args!(", {column} = ")).push_bind(value);
+        }
+    }
+    if let Some(metadata) = patch.metadata.update() {
+        builder.push(", metadata = COALESCE(").push_bind(metadata).push(", '{}'::jsonb)");
+    }
+    builder
+        .push(" WHERE id = ")
+        .push_bind(user_id)
+        .push(format_args!(" RETURNING {PROFILE_COLUMNS}"));
+
+    timed(builder.build_query_as::<UserProfile>().fetch_optional(executor))
+        .await
+        .map_err(DbError::ProfileUpdateError)?
+        .ok_or(DbError::ProfileNotFound)
+}
+
+/// Sort keys of the admin profile listing. Text keys sort case-insensitively, with
+/// missing values first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfileSort {
+    CreatedAt,
+    UpdatedAt,
+    Email,
+    Username,
+}
+
+impl SortKey for ProfileSort {
+    const ALL: &'static [Self] = &[ProfileSort::CreatedAt, ProfileSort::UpdatedAt, ProfileSort::Email, ProfileSort::Username];
+    const DEFAULT: Sort<Self> = Sort { key: ProfileSort::CreatedAt, descending: true };
+
+    fn name(self) -> &'static str {
+        match self {
+            ProfileSort::CreatedAt => "created_at",
+            ProfileSort::UpdatedAt => "updated_at",
+            ProfileSort::Email => "email",
+            ProfileSort::Username => "username",
+        }
+    }
+
+    fn expression(self) -> &'static str {
+        match self {
+            ProfileSort::CreatedAt => "created_at",
+            ProfileSort::UpdatedAt => "updated_at",
+            ProfileSort::Email => "lower(coalesce(email, ''))",
+            ProfileSort::Username => "lower(coalesce(username, ''))",
+        }
+    }
+
+    fn sql_type(self) -> &'static str {
+        match self {
+            ProfileSort::CreatedAt | ProfileSort::UpdatedAt => "timestamptz",
+            ProfileSort::Email | ProfileSort::Username => "text",
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct ListedProfile {
+    #[sqlx(flatten)]
+    profile: UserProfile,
+    sort_value: String,
+}
+
+/// Lists profiles for admins: one page plus the total number of matches. Runs two
+/// queries, so pass the pool rather than an RLS transaction.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_profiles(
+    pool: &PgPool,
+    filter: &ProfileFilter,
+    list: &ListQuery<ProfileSort>,
+) -> Result<Page<UserProfile>, DbError> {
+    let mut count = QueryBuilder::new("SELECT count(*) FROM public.profiles");
+    push_filter(&mut count, filter);
+    let total = timed(count.build_query_scalar::<i64>().fetch_one(pool)).await?;
+
+    let mut select = QueryBuilder::new(format!(
+        "SELECT {PROFILE_COLUMNS}, {} FROM public.profiles",
+        list.sort_value_column()
+    ));
+    push_filter(&mut select, filter);
+    list.push_page(&mut select);
+    let rows = timed(select.build_query_as::<ListedProfile>().fetch_all(pool)).await?;
+
+    let rows = rows.into_iter().map(|row| (row.profile, row.sort_value)).collect();
+    Ok(list.build_page(rows, total, |profile| profile.id))
+}
+
+/// Appends a `WHERE` clause for `filter` (always, so conditions can follow).
+fn push_filter<'a>(builder: &mut QueryBuilder<'a, Postgres>, filter: &'a ProfileFilter) {
+    builder.push(" WHERE true");
+    if let Some(email) = &filter.email {
+        builder.push(" AND email ILIKE ").push_bind(listing::contains_pattern(email));
+    }
+    if let Some(username) = &filter.username {
+        builder.push(" AND username ILIKE ").push_bind(listing::contains_pattern(username));
+    }
+    if let Some(after) = filter.created_after {
+        builder.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = filter.created_before {
+        builder.push(" AND created_at < ").push_bind(before);
+    }
+    if let Some(role) = filter.role {
+        builder
+            .push(" AND coalesce((SELECT role FROM public.user_roles WHERE user_id = profiles.id), 'user') = ")
+            .push_bind(role.to_string());
+    }
+}
+
+/// Finds profiles by username or full name, best matches first. Every word of `text`
+/// matches as a prefix ("ada lov" finds "Ada Lovelace"); trigram similarity also finds
+/// near misses such as "lovelance". Pass the pool: under RLS only the caller's own
+/// profile is visible. Returns nothing when `text` has no letters or digits.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn search_profiles(executor: impl PgExecutor<'_>, text: &str, limit: i64) -> Result<Vec<UserProfile>, DbError> {
+    let Some(prefixes) = prefix_tsquery(text) else {
+        return Ok(Vec::new());
+    };
+    Ok(timed(query_as::<_, UserProfile>(&format!(
+        "SELECT {PROFILE_COLUMNS}
+        FROM public.profiles, to_tsquery('simple', $2) AS prefixes
+        WHERE search_vector @@ prefixes OR username % $1 OR full_name % $1
+        ORDER BY coalesce(lower(username) = lower($1), false)::int
+            + ts_rank(search_vector, prefixes)
+            + greatest(similarity(coalesce(username, ''), $1), similarity(coalesce(full_name, ''), $1)) DESC,
+            id
+        LIMIT $3"
+    ))
+    .bind(text)
+    .bind(prefixes)
+    .bind(limit)
+    .fetch_all(executor))
+    .await?)
+}
+
+/// `to_tsquery` input requiring every word of `text` as a prefix, e.g. `ada:* & lov:*`.
+/// Only letters and digits are kept, so user input cannot inject tsquery syntax.
+fn prefix_tsquery(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect();
+    if words.is_empty() { None } else { Some(words.join(" & ")) }
+}
+
+/// Whether a profile other than `except` uses `username`, ignoring case. Pass the pool:
+/// under RLS a user cannot see the other profiles.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn username_taken(executor: impl PgExecutor<'_>, username: &str, except: Uuid) -> Result<bool, DbError> {
+    Ok(timed(query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM public.profiles WHERE lower(username) = lower($1) AND id <> $2)"
+    )
+    .bind(username)
+    .bind(except)
+    .fetch_one(executor))
+    .await?)
+}
+
+/// Reads a profile's `metadata` and locks the row until the transaction ends, so a
+/// merge patch computed from it cannot overwrite a concurrent change.
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn lock_metadata(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Value, DbError> {
+    timed(query_scalar(
+        "SELECT metadata FROM public.profiles WHERE id = $1 FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(executor))
+    .await?
+    .ok_or(DbError::ProfileNotFound)
+}
+
 /// Deletes a user profile by its ID.
-pub async fn delete_profile(pool: &PgPool, user_id: Uuid) -> Result<(), DbError> {
-    let result = query(
+#[tracing::instrument(level = "debug", skip_all, fields(%user_id))]
+pub async fn delete_profile(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<(), DbError> {
+    let result = timed(query(
         "DELETE FROM public.profiles WHERE id = $1"
     )
     .bind(user_id)
-    .execute(pool)
+    .execute(executor))
     .await
     .map_err(DbError::ProfileDeleteError)?;
 
@@ -139,4 +392,58 @@ CREATE POLICY "Users can update their own profile" ON public.profiles
 CREATE POLICY "Users can delete their own profile" ON public.profiles
   FOR DELETE
   USING (auth.uid() = id);
-*/ 
\ No newline at end of file
+*/ 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_pool, test_user};
+
+    async fn insert_profile(pool: &PgPool, username: Option<&str>, full_name: Option<&str>) -> Uuid {
+        let id = test_user(pool).await;
+        let payload = CreateProfilePayload {
+            username: username.map(str::to_string),
+            full_name: full_name.map(str::to_string),
+            ..Default::default()
+        };
+        create_profile(pool, id, Some(format!("{}@example.com", id)), payload).await.unwrap();
+        id
+    }
+
+    /// Letters and digits unique to one test, so searches only find that test's rows.
+    fn unique_word() -> String {
+        format!("q{}", &Uuid::new_v4().simple().to_string()[..10])
+    }
+
+    async fn search_ids(pool: &PgPool, text: &str) -> Vec<Uuid> {
+        search_profiles(pool, text, 10).await.unwrap().into_iter().map(|profile| profile.id).collect()
+    }
+
+    #[test]
+    fn test_prefix_tsquery_keeps_only_words() {
+        assert_eq!(prefix_tsquery("Ada  Lov").as_deref(), Some("ada:* & lov:*"));
+        assert_eq!(prefix_tsquery("o'brien | !x:*").as_deref(), Some("o:* & brien:* & x:*"));
+        assert_eq!(prefix_tsquery(" & :* ").as_deref(), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_exact_username_above_prefix_and_near_misses() {
+        let Some(pool) = test_pool().await else { return };
+        let word = unique_word();
+        let near_miss = insert_profile(&pool, Some(&format!("{}z", &word[..10])), None).await;
+        let prefix = insert_profile(&pool, Some(&format!("{}_ab", word)), None).await;
+        let exact = insert_profile(&pool, Some(&word), None).await;
+
+        assert_eq!(search_ids(&pool, &word.to_uppercase()).await, vec![exact, prefix, near_miss]);
+    }
+
+    #[tokio::test]
+    async fn test_search_does_not_rank_null_usernames_first() {
+        let Some(pool) = test_pool().await else { return };
+        let word = unique_word();
+        let no_username = insert_profile(&pool, None, Some(&format!("{} Lovelace", word))).await;
+        let exact = insert_profile(&pool, Some(&word), None).await;
+
+        assert_eq!(search_ids(&pool, &word).await, vec![exact, no_username]);
+    }
+}
@@ -0,0 +1,34 @@
+use sqlx::pool::PoolConnection;
+use sqlx::{Postgres, Transaction};
+
+use super::DbError;
+use crate::auth::user_context::AuthUser;
+
+/// Postgres role Supabase uses for requests carrying a user JWT.
+/// The connection's login role must be a member of it (true for `postgres` on Supabase).
+const AUTHENTICATED_ROLE: &str = "authenticated";
+
+/// Begins a transaction that runs as the `authenticated` role with the request's
+/// verified JWT claims, the same way PostgREST does, so RLS policies using
+/// `auth.uid()`, `auth.role()` and `auth.jwt()` apply to every query in it.
+///
+/// The settings are transaction-local (`SET LOCAL` semantics) and are discarded on
+/// commit or rollback, so the pooled connection is never left impersonating a user.
+pub async fn begin_as_user(conn: PoolConnection<Postgres>, user: &AuthUser) -> Result<Transaction<'static, Postgres>, DbError> {
+    let mut tx = Transaction::begin(conn).await?;
+
+    // `set_config(.., true)` is the parameterizable form of `SET LOCAL`.
+    sqlx::query(
+        "SELECT set_config('role', $1, true),
+            set_config('request.jwt.claims', $2, true),
+            set_config('request.jwt.claim.sub', $3, true),
+            set_config('request.jwt.claim.role', $1, true)"
+    )
+    .bind(AUTHENTICATED_ROLE)
+    .bind(user.claims.to_string())
+    .bind(&user.id)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(tx)
+}
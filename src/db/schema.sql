@@ -1,14 +1,52 @@
 -- SQL schema for public.profiles table
 -- Ensure this table is created in your Supabase PostgreSQL database.
+-- `/readyz` checks for the objects the service uses (REQUIRED_* in src/db.rs); update
+-- those lists when adding or renaming tables, columns or indexes here.
 
 CREATE TABLE IF NOT EXISTS public.profiles (
     id UUID PRIMARY KEY REFERENCES auth.users(id) ON DELETE CASCADE,
     email TEXT UNIQUE,
     username TEXT UNIQUE,
+    full_name TEXT CHECK (char_length(full_name) <= 100),
+    avatar_url TEXT CHECK (char_length(avatar_url) <= 2048),
+    bio TEXT CHECK (char_length(bio) <= 500),
+    locale TEXT CHECK (char_length(locale) <= 35),
+    timezone TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}' CHECK (jsonb_typeof(metadata) = 'object'),
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', coalesce(username, '') || ' ' || coalesce(full_name, ''))
+    ) STORED,
     created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
 );
 
+-- Upgrades databases created before the profile detail columns existed.
+-- The limits match src/validation.rs, which also checks locale, timezone and the metadata schema.
+ALTER TABLE public.profiles
+    ADD COLUMN IF NOT EXISTS full_name TEXT CHECK (char_length(full_name) <= 100),
+    ADD COLUMN IF NOT EXISTS avatar_url TEXT CHECK (char_length(avatar_url) <= 2048),
+    ADD COLUMN IF NOT EXISTS bio TEXT CHECK (char_length(bio) <= 500),
+    ADD COLUMN IF NOT EXISTS locale TEXT CHECK (char_length(locale) <= 35),
+    ADD COLUMN IF NOT EXISTS timezone TEXT,
+    ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}' CHECK (jsonb_typeof(metadata) = 'object');
+
+-- Usernames keep their case but are unique regardless of it. The API reports violations
+-- as 409 with `"field": "username"` (src/error.rs matches on this index name).
+-- On an existing database, rename usernames that differ only in case before running this.
+CREATE UNIQUE INDEX IF NOT EXISTS profiles_username_lower_key ON public.profiles (lower(username));
+
+-- Profile search (GET /api/profiles/search): word prefixes through the tsvector, typos
+-- through trigram similarity. The 'simple' configuration does not stem, which suits names.
+-- On Supabase, pg_trgm can also be enabled from the dashboard (Database > Extensions).
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+ALTER TABLE public.profiles
+    ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', coalesce(username, '') || ' ' || coalesce(full_name, ''))
+    ) STORED;
+CREATE INDEX IF NOT EXISTS profiles_search_vector_idx ON public.profiles USING gin (search_vector);
+CREATE INDEX IF NOT EXISTS profiles_search_trgm_idx ON public.profiles
+    USING gin (username gin_trgm_ops, full_name gin_trgm_ops);
+
 -- Trigger to update `updated_at` timestamp automatically
 CREATE OR REPLACE FUNCTION public.handle_updated_at() 
 RETURNS TRIGGER AS $$
@@ -26,6 +64,10 @@ CREATE TRIGGER on_profile_updated
 -- Enable Row Level Security (RLS) on the profiles table.
 ALTER TABLE public.profiles ENABLE ROW LEVEL SECURITY;
 
+-- The API runs profile queries as the `authenticated` role with the caller's JWT claims
+-- (see src/db/rls.rs), so these policies are enforced for self-service routes.
+GRANT SELECT, INSERT, UPDATE, DELETE ON public.profiles TO authenticated;
+
 -- Policies for RLS:
 -- 1. Users can view their own profile.
 CREATE POLICY "Users can view their own profile" ON public.profiles
@@ -46,4 +88,45 @@ CREATE POLICY "Users can update their own profile" ON public.profiles
 -- 4. Users can delete their own profile (optional).
 CREATE POLICY "Users can delete their own profile" ON public.profiles
   FOR DELETE
-  USING (auth.uid() = id); 
\ No newline at end of file
+  USING (auth.uid() = id); 
+
+-- Roles, permissions and tenant per user.
+-- Read by the custom access token hook (POST /hooks/custom-access-token) to populate JWT claims.
+CREATE TABLE IF NOT EXISTS public.user_roles (
+    user_id UUID PRIMARY KEY REFERENCES auth.users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'premium', 'admin')),
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    tenant_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER on_user_role_updated
+  BEFORE UPDATE ON public.user_roles
+  FOR EACH ROW
+  EXECUTE PROCEDURE public.handle_updated_at();
+
+ALTER TABLE public.user_roles ENABLE ROW LEVEL SECURITY;
+GRANT SELECT ON public.user_roles TO authenticated;
+
+-- Users can read their own role. Writes are reserved for the service (admin routes).
+CREATE POLICY "Users can view their own role" ON public.user_roles
+  FOR SELECT
+  USING (auth.uid() = user_id);
+
+-- Admin changes to other users' profiles (PUT/PATCH/DELETE /api/profiles/:user_id).
+-- No foreign keys, so entries outlive deleted profiles and admins.
+-- `changes` maps each changed field to {"old": ..., "new": ...}.
+CREATE TABLE IF NOT EXISTS public.profile_audit_log (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    actor_id UUID NOT NULL,
+    profile_id UUID NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('update', 'patch', 'delete')),
+    changes JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS profile_audit_log_profile_idx ON public.profile_audit_log (profile_id, created_at DESC);
+
+-- RLS without policies: only the service's own role can read or write the log.
+ALTER TABLE public.profile_audit_log ENABLE ROW LEVEL SECURITY;
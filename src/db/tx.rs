@@ -0,0 +1,110 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use super::{rls, DbError};
+use crate::auth::user_context::AuthUser;
+use crate::metrics::Metrics;
+
+type TxCell = Arc<Mutex<Option<Transaction<'static, Postgres>>>>;
+
+/// Per-request state installed by `tx_middleware` and shared with the `Tx` extractor.
+#[derive(Clone)]
+struct TxSlot {
+    pool: PgPool,
+    metrics: Arc<Metrics>,
+    user: Option<AuthUser>,
+    tx: TxCell,
+}
+
+/// Request-scoped transaction. Nothing is sent to the database until `conn` is first
+/// called. When the request carries an `AuthUser`, the transaction runs under RLS
+/// (see `rls::begin_as_user`).
+///
+/// `tx_middleware` commits the transaction if the handler returns a 2xx or 3xx
+/// response and rolls it back otherwise. If the handler panics, the transaction is
+/// dropped unfinished and sqlx rolls it back when the connection returns to the pool.
+pub struct Tx {
+    slot: TxSlot,
+    guard: OwnedMutexGuard<Option<Transaction<'static, Postgres>>>,
+}
+
+impl Tx {
+    /// The connection to pass to repository functions, beginning the transaction on first use.
+    pub async fn conn(&mut self) -> Result<&mut PgConnection, DbError> {
+        if self.guard.is_none() {
+            let started = Instant::now();
+            let conn = self.slot.pool.acquire().await?;
+            self.slot.metrics.observe_tx_acquire(started.elapsed());
+            let tx = match &self.slot.user {
+                Some(user) => rls::begin_as_user(conn, user).await?,
+                None => Transaction::begin(conn).await?,
+            };
+            *self.guard = Some(tx);
+        }
+        match self.guard.as_mut() {
+            Some(tx) => Ok(&mut **tx),
+            None => unreachable!("transaction was just started"),
+        }
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for Tx
+where
+    S: Send + Sync,
+{
+    type Rejection = DbError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let slot = parts
+            .extensions
+            .get::<TxSlot>()
+            .cloned()
+            .ok_or(DbError::TransactionUnavailable("Is tx_middleware applied?"))?;
+        // A second `Tx` in the same handler would otherwise wait for the first forever.
+        let guard = slot
+            .tx
+            .clone()
+            .try_lock_owned()
+            .map_err(|_| DbError::TransactionUnavailable("Tx may only be extracted once per request"))?;
+        Ok(Tx { slot, guard })
+    }
+}
+
+/// Provides the `Tx` extractor to the routes it wraps and finishes the transaction
+/// once the handler has produced its response. Must run inside the auth middleware
+/// for the transaction to pick up the user's claims.
+pub async fn tx_middleware(
+    State(pool): State<PgPool>,
+    State(metrics): State<Arc<Metrics>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let tx: TxCell = Arc::new(Mutex::new(None));
+    let user = req.extensions().get::<AuthUser>().cloned();
+    req.extensions_mut().insert(TxSlot { pool, metrics, user, tx: tx.clone() });
+
+    let response = next.run(req).await;
+
+    // The handler (and with it the extractor's guard) has been dropped by now.
+    let Some(tx) = tx.lock().await.take() else {
+        return response;
+    };
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        if let Err(e) = tx.commit().await {
+            return DbError::QueryError(e).into_response();
+        }
+    } else if let Err(e) = tx.rollback().await {
+        tracing::error!(error = %e, "Failed to roll back request transaction");
+    }
+    response
+}
@@ -0,0 +1,36 @@
+use sqlx::{query_as, PgExecutor};
+use uuid::Uuid;
+
+use super::models::UserRoleRecord;
+use super::DbError;
+use crate::auth::user_context::UserRole;
+
+/// Fetches the role record for a user, if one has been assigned.
+pub async fn get_user_role(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Option<UserRoleRecord>, DbError> {
+    let record = query_as::<_, UserRoleRecord>(
+        "SELECT user_id, role, permissions, tenant_id
+        FROM public.user_roles
+        WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+/// Sets a user's role, creating the record if needed. Permissions and tenant are left untouched.
+pub async fn upsert_user_role(executor: impl PgExecutor<'_>, user_id: Uuid, role: UserRole) -> Result<UserRoleRecord, DbError> {
+    let record = query_as::<_, UserRoleRecord>(
+        "INSERT INTO public.user_roles (user_id, role)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET role = EXCLUDED.role
+        RETURNING user_id, role, permissions, tenant_id"
+    )
+    .bind(user_id)
+    .bind(role.to_string())
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record)
+}
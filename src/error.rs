@@ -0,0 +1,487 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::fmt::Display;
+use utoipa::ToSchema;
+
+use crate::auth::error::{AuthError, AuthFailure};
+use crate::auth::gotrue::GoTrueError;
+use crate::avatar::AvatarError;
+use crate::db::DbError;
+use crate::request_id;
+use crate::storage::StorageError;
+use crate::validation::ValidationErrors;
+
+/// Application-wide error (DEV-PLAN 4.1). Rendered as an RFC 7807
+/// `application/problem+json` body with a stable, machine-readable `code`.
+/// Only `detail` reaches the client; internal messages are logged instead.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{detail}")]
+    BadRequest { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    Unauthorized { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    Forbidden { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    NotFound { code: &'static str, detail: String },
+
+    /// `field` names the request field that clashed with an existing row, if known.
+    #[error("{detail}")]
+    Conflict { code: &'static str, detail: String, field: Option<&'static str> },
+
+    #[error("{detail}")]
+    PayloadTooLarge { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    UnsupportedMediaType { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    Unprocessable { code: &'static str, detail: String },
+
+    #[error("Too many requests")]
+    TooManyRequests { retry_after_secs: u64 },
+
+    #[error("{detail}")]
+    BadGateway { code: &'static str, detail: String },
+
+    #[error("{detail}")]
+    ServiceUnavailable { code: &'static str, detail: String, retry_after_secs: Option<u64> },
+
+    /// A handler ran past its route's timeout.
+    #[error("{detail}")]
+    GatewayTimeout { code: &'static str, detail: String },
+
+    /// `message` is logged with the request id and never returned.
+    #[error("Internal error: {message}")]
+    Internal { code: &'static str, message: String },
+}
+
+impl AppError {
+    pub fn bad_request(code: &'static str, detail: impl Into<String>) -> Self {
+        AppError::BadRequest { code, detail: detail.into() }
+    }
+
+    pub fn unauthorized(code: &'static str, detail: impl Into<String>) -> Self {
+        AppError::Unauthorized { code, detail: detail.into() }
+    }
+
+    pub fn forbidden(code: &'static str, detail: impl Into<String>) -> Self {
+        AppError::Forbidden { code, detail: detail.into() }
+    }
+
+    pub fn not_found(code: &'static str, detail: impl Into<String>) -> Self {
+        AppError::NotFound { code, detail: detail.into() }
+    }
+
+    pub fn conflict(code: &'static str, detail: impl Into<String>) -> Self {
+        AppError::Conflict { code, detail: detail.into(), field: None }
+    }
+
+    pub fn internal(code: &'static str, message: impl Display) -> Self {
+        AppError::Internal { code, message: message.to_string() }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Unprocessable { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::BadGateway { .. } => StatusCode::BAD_GATEWAY,
+            AppError::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::GatewayTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable identifier clients can match on; never changes for a given condition.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest { code, .. }
+            | AppError::Unauthorized { code, .. }
+            | AppError::Forbidden { code, .. }
+            | AppError::NotFound { code, .. }
+            | AppError::Conflict { code, .. }
+            | AppError::PayloadTooLarge { code, .. }
+            | AppError::UnsupportedMediaType { code, .. }
+            | AppError::Unprocessable { code, .. }
+            | AppError::BadGateway { code, .. }
+            | AppError::ServiceUnavailable { code, .. }
+            | AppError::GatewayTimeout { code, .. }
+            | AppError::Internal { code, .. } => code,
+            AppError::TooManyRequests { .. } => "rate_limited",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            AppError::Internal { .. } => "An internal error occurred".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            AppError::Conflict { field, .. } => *field,
+            _ => None,
+        }
+    }
+
+    fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            AppError::TooManyRequests { retry_after_secs } => Some(*retry_after_secs),
+            AppError::ServiceUnavailable { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        }
+    }
+}
+
+/// RFC 7807 problem details, plus the `code` and `request_id` extension members.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "type": "about:blank",
+    "title": "Not Found",
+    "status": 404,
+    "detail": "Profile not found",
+    "code": "profile_not_found",
+    "request_id": "0b6f7c1e-4a53-4c1e-9d0e-0f5a1d2c3b4a"
+}))]
+pub struct Problem {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    /// Stable, machine-readable error code.
+    code: &'static str,
+    /// The request field at fault, for conflicts on a unique field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'static str>,
+    /// Matches the `X-Request-Id` response header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let request_id = request_id::current();
+        if let AppError::Internal { code, message } = &self {
+            tracing::error!(code, error = %message, "Internal error");
+        }
+
+        let problem = Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            field: self.field(),
+            request_id,
+        };
+        let mut response = (status, Json(problem)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        if let Some(secs) = self.retry_after_secs() {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::MissingToken => AppError::unauthorized("missing_token", err.to_string()),
+            AuthError::InvalidTokenFormat => AppError::unauthorized("invalid_token_format", err.to_string()),
+            // The reason comes from jsonwebtoken or the JWK and is only useful in the logs.
+            AuthError::InvalidToken(reason) => {
+                tracing::info!(%reason, "Rejected invalid access token");
+                AppError::unauthorized("invalid_token", "The access token is invalid")
+            }
+            AuthError::TokenExpired => AppError::unauthorized("token_expired", "Token has expired"),
+            AuthError::TokenClaimInvalid { .. } => AppError::unauthorized("invalid_claim", err.to_string()),
+            // Usually a token issued by another project, or keys rotated before the next refresh.
+            AuthError::JwkKidNotFound { .. } => AppError::unauthorized("unknown_signing_key", "Could not verify token (key not found)"),
+            AuthError::Forbidden(_) => AppError::forbidden("forbidden", err.to_string()),
+            AuthError::KeysNotReady => AppError::ServiceUnavailable {
+                code: "keys_not_ready",
+                detail: "Service is starting, signing keys not loaded yet".to_string(),
+                // Keys are normally loaded within seconds of startup.
+                retry_after_secs: Some(5),
+            },
+            AuthError::JwksProcessingError(e) => AppError::internal("signing_keys_error", e),
+            AuthError::InternalError(message) => AppError::internal("internal_error", message),
+        }
+    }
+}
+
+impl From<DbError> for AppError {
+    fn from(err: DbError) -> Self {
+        match err {
+            DbError::ProfileNotFound => AppError::not_found("profile_not_found", "Profile not found"),
+            DbError::ProfileCreationError(e) | DbError::ProfileUpdateError(e) if is_unique_violation(&e) => {
+                match unique_field(&e) {
+                    Some(field) => AppError::Conflict {
+                        code: "already_taken",
+                        detail: format!("This {} is already taken", field),
+                        field: Some(field),
+                    },
+                    None => AppError::conflict("profile_exists", "Profile already exists"),
+                }
+            }
+            DbError::PoolCreationFailed(e) | DbError::ConnectionError(e) | DbError::QueryError(e)
+                if matches!(e, sqlx::Error::PoolTimedOut | sqlx::Error::Io(_)) =>
+            {
+                tracing::error!(error = %e, "Database unavailable");
+                AppError::ServiceUnavailable {
+                    code: "database_unavailable",
+                    detail: "Database is temporarily unavailable".to_string(),
+                    retry_after_secs: Some(1),
+                }
+            }
+            err => AppError::internal("database_error", err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        DbError::QueryError(err).into()
+    }
+}
+
+impl From<GoTrueError> for AppError {
+    fn from(err: GoTrueError) -> Self {
+        match err {
+            GoTrueError::NotConfigured => AppError::ServiceUnavailable {
+                code: "admin_api_not_configured",
+                detail: err.to_string(),
+                retry_after_secs: None,
+            },
+            GoTrueError::UserNotFound => AppError::not_found("user_not_found", err.to_string()),
+            // Client errors from GoTrue (duplicate email, weak password, ...) are meaningful to the admin.
+            GoTrueError::Api { status, message } if status.is_client_error() => {
+                AppError::Unprocessable { code: "auth_request_rejected", detail: message }
+            }
+            GoTrueError::Network(e) => {
+                tracing::error!(error = %e, "Auth admin API unreachable");
+                AppError::BadGateway { code: "auth_service_unavailable", detail: "Auth service unavailable".to_string() }
+            }
+            GoTrueError::Api { status, message } => {
+                tracing::error!(%status, %message, "Auth admin API error");
+                AppError::BadGateway { code: "auth_service_error", detail: "Auth service error".to_string() }
+            }
+        }
+    }
+}
+
+impl From<StorageError> for AppError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::Io(e) => AppError::internal("storage_error", e),
+            err => {
+                tracing::error!(error = %err, "Storage service error");
+                AppError::BadGateway { code: "storage_unavailable", detail: "File storage unavailable".to_string() }
+            }
+        }
+    }
+}
+
+impl From<AvatarError> for AppError {
+    fn from(err: AvatarError) -> Self {
+        match err {
+            AvatarError::UnsupportedFormat => AppError::UnsupportedMediaType { code: "unsupported_image", detail: err.to_string() },
+            AvatarError::Decode(_) => AppError::Unprocessable { code: "invalid_image", detail: err.to_string() },
+        }
+    }
+}
+
+// Rejections from axum's extractors, so malformed requests get the same problem+json
+// shape as every other error. Their text describes the request, not server internals.
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        let detail = rejection.body_text();
+        match rejection {
+            // Valid JSON that does not fit the payload: a missing field or a wrong type.
+            JsonRejection::JsonDataError(_) => AppError::Unprocessable { code: "validation_failed", detail },
+            JsonRejection::MissingJsonContentType(_) => AppError::UnsupportedMediaType { code: "invalid_body", detail },
+            JsonRejection::BytesRejection(_) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                AppError::PayloadTooLarge { code: "payload_too_large", detail }
+            }
+            _ => AppError::bad_request("invalid_body", detail),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::bad_request("invalid_query", rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        match rejection {
+            PathRejection::FailedToDeserializePathParams(e) => AppError::bad_request("invalid_path", e.body_text()),
+            // The route does not declare the parameter: a programming error, not a bad request.
+            e => AppError::internal("path_extraction_failed", e.body_text()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(err: ValidationErrors) -> Self {
+        AppError::Unprocessable { code: "validation_failed", detail: err.to_string() }
+    }
+}
+
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    err.as_database_error().is_some_and(|e| e.is_unique_violation())
+}
+
+/// The profile field behind a unique violation, from the constraint and index names in
+/// `schema.sql`. `None` for the primary key, i.e. the profile itself already exists.
+fn unique_field(err: &sqlx::Error) -> Option<&'static str> {
+    match err.as_database_error()?.constraint()? {
+        "profiles_username_key" | "profiles_username_lower_key" => Some("username"),
+        "profiles_email_key" => Some("email"),
+        _ => None,
+    }
+}
+
+// Extractor rejections and middleware return these errors directly.
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let kind = self.kind();
+        let mut response = AppError::from(self).into_response();
+        response.extensions_mut().insert(AuthFailure(kind));
+        response
+    }
+}
+
+impl IntoResponse for DbError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
+
+impl IntoResponse for GoTrueError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use serde_json::Value;
+
+    async fn body(response: Response) -> Value {
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    /// A unique violation on `constraint`, as Postgres reports it.
+    #[derive(Debug, thiserror::Error)]
+    #[error("duplicate key value violates unique constraint \"{0}\"")]
+    struct UniqueViolation(&'static str);
+
+    impl sqlx::error::DatabaseError for UniqueViolation {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint"
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn constraint(&self) -> Option<&str> {
+            Some(self.0)
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            sqlx::error::ErrorKind::UniqueViolation
+        }
+    }
+
+    fn unique_violation(constraint: &'static str) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(UniqueViolation(constraint)))
+    }
+
+    #[tokio::test]
+    async fn test_unique_violations_name_the_conflicting_field() {
+        for constraint in ["profiles_username_key", "profiles_username_lower_key"] {
+            let response = AppError::from(DbError::ProfileUpdateError(unique_violation(constraint))).into_response();
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            let body = body(response).await;
+            assert_eq!((&body["code"], &body["field"]), (&Value::from("already_taken"), &Value::from("username")), "{}", constraint);
+            assert_eq!(body["detail"], "This username is already taken");
+        }
+
+        let email = body(AppError::from(DbError::ProfileCreationError(unique_violation("profiles_email_key"))).into_response()).await;
+        assert_eq!((&email["code"], &email["field"]), (&Value::from("already_taken"), &Value::from("email")));
+
+        let response = AppError::from(DbError::ProfileCreationError(unique_violation("profiles_pkey"))).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let existing = body(response).await;
+        assert_eq!(existing["code"], "profile_exists");
+        assert!(existing.get("field").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_problem_json_hides_internal_details() {
+        let response = AppError::from(DbError::QueryError(sqlx::Error::RowNotFound)).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+
+        let body = body(response).await;
+        assert_eq!(body["code"], "database_error");
+        assert_eq!(body["status"], 500);
+        assert_eq!(body["detail"], "An internal error occurred");
+        assert!(!body.to_string().contains("no rows"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_errors_keep_stable_codes() {
+        let response = AuthError::KeysNotReady.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+        assert_eq!(body(response).await["code"], "keys_not_ready");
+
+        let body = body(AuthError::TokenExpired.into_response()).await;
+        assert_eq!(body["code"], "token_expired");
+        assert_eq!(body["title"], "Unauthorized");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token_reason_is_not_returned() {
+        let reason = "JWK type does not match token algorithm RS256".to_string();
+        let response = AuthError::InvalidToken(reason).into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = body(response).await;
+        assert_eq!(body["code"], "invalid_token");
+        assert_eq!(body["detail"], "The access token is invalid");
+    }
+}
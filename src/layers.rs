@@ -0,0 +1,183 @@
+use axum::{
+    extract::{DefaultBodyLimit, MatchedPath, Request, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use crate::config::{AppConfig, CorsConfig};
+use crate::error::AppError;
+use crate::request_id::REQUEST_ID_HEADER;
+use crate::state::AppState;
+
+/// Adds the body limit, compression, security headers and CORS to the router.
+/// Applied with `Router::layer`, so only routes added before this call are covered.
+pub fn apply(router: Router<AppState>, config: &AppConfig) -> Router<AppState> {
+    let http = &config.http;
+    // Routes that accept larger bodies (uploads) set their own `DefaultBodyLimit`, which wins.
+    let mut router = router.layer(DefaultBodyLimit::max(http.max_body_bytes));
+    if http.compression {
+        router = router.layer(CompressionLayer::new());
+    }
+
+    router = router
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ))
+        .layer(SetResponseHeaderLayer::if_not_present(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")))
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::REFERRER_POLICY,
+            HeaderValue::from_static("no-referrer"),
+        ));
+    if http.hsts_max_age_secs > 0 {
+        let hsts = HeaderValue::from_str(&format!("max-age={}", http.hsts_max_age_secs))
+            .expect("valid header value");
+        router = router.layer(SetResponseHeaderLayer::if_not_present(header::STRICT_TRANSPORT_SECURITY, hsts));
+    }
+
+    // Outermost, so preflight requests are answered before they reach auth or rate limiting.
+    match cors_layer(&config.cors) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// CORS for the configured origins, or `None` (same-origin only) when none are set.
+/// Values are checked by `AppConfig::validate`.
+pub fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.allowed_origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+    let headers: Vec<HeaderName> =
+        config.allowed_headers.iter().filter_map(|name| HeaderName::try_from(name.as_str()).ok()).collect();
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+            .allow_headers(headers)
+            .expose_headers([
+                REQUEST_ID_HEADER.clone(),
+                header::RETRY_AFTER,
+                HeaderName::from_static("ratelimit-limit"),
+                HeaderName::from_static("ratelimit-remaining"),
+                HeaderName::from_static("ratelimit-reset"),
+            ])
+            .allow_credentials(config.allow_credentials)
+            .max_age(Duration::from_secs(config.max_age_secs)),
+    )
+}
+
+/// Bounds the time spent on a request, using the route's timeout from `http.route_timeouts`
+/// or the default. Must be applied with `Router::layer` so `MatchedPath` is available.
+/// Dropping the handler rolls back its request transaction.
+pub async fn timeout_middleware(State(config): State<Arc<AppConfig>>, req: Request, next: Next) -> Response {
+    let route = match req.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().to_string(),
+        None => req.uri().path().to_string(),
+    };
+    let timeout = config.http.timeout_for(&route);
+
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(%route, timeout_secs = timeout.as_secs(), "Request timed out");
+            AppError::GatewayTimeout {
+                code: "request_timeout",
+                detail: format!("The request did not complete within {} seconds", timeout.as_secs()),
+            }
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get};
+    use tower::ServiceExt;
+
+    fn preflight(origin: &str) -> Request {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/profiles/me")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization,content-type")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_and_security_headers() {
+        let mut config = AppConfig::default();
+        config.cors.allowed_origins = vec!["https://app.example.com".to_string()];
+        let router = Router::new().route("/api/profiles/me", get(|| async { "me" }).put(|| async { "updated" }));
+        let app = apply(router, &config).with_state(AppState::new(
+            sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap(),
+            config,
+        ));
+
+        let response = app.clone().oneshot(preflight("https://app.example.com")).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("PUT"));
+
+        let response = app.clone().oneshot(preflight("https://evil.example.com")).await.unwrap();
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let request = Request::get("/api/profiles/me").header(header::ORIGIN, "https://app.example.com");
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let headers = response.headers();
+        assert!(headers[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap().contains("x-request-id"));
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+    }
+
+    #[test]
+    fn test_longest_route_prefix_sets_the_timeout() {
+        let mut config = AppConfig::default();
+        config.http.route_timeouts.insert("/api".to_string(), 10);
+        config.http.route_timeouts.insert("/api/admin".to_string(), 60);
+        assert_eq!(config.http.timeout_for("/api/admin/users"), Duration::from_secs(60));
+        assert_eq!(config.http.timeout_for("/api/profiles/me"), Duration::from_secs(10));
+        assert_eq!(config.http.timeout_for("/healthz"), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_times_out_with_gateway_timeout() {
+        let mut config = AppConfig::default();
+        config.http.route_timeouts.insert("/slow".to_string(), 1);
+        let state = AppState::new(
+            sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap(),
+            config,
+        );
+        let slow = || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "done"
+        };
+        let app = Router::new()
+            .route("/slow", get(slow))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), timeout_middleware))
+            .with_state(state);
+
+        let response = app.oneshot(Request::get("/slow").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let problem: serde_json::Value =
+            serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(problem["code"], "request_timeout");
+    }
+}
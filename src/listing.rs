@@ -0,0 +1,326 @@
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+// Query-string conventions shared by list endpoints: `sort=-created_at`, `limit`, and
+// either `offset` or an opaque `cursor` from a previous page's `next_cursor`.
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ListQueryError {
+    #[error("Unknown sort key '{given}'; expected one of: {expected}")]
+    UnknownSortKey { given: String, expected: String },
+
+    #[error("limit must be between 1 and {MAX_LIMIT}")]
+    InvalidLimit,
+
+    #[error("offset and cursor cannot be combined")]
+    OffsetWithCursor,
+
+    #[error("cursor is malformed or was issued for a different sort")]
+    InvalidCursor,
+}
+
+impl From<ListQueryError> for AppError {
+    fn from(err: ListQueryError) -> Self {
+        AppError::bad_request("invalid_list_query", err.to_string())
+    }
+}
+
+/// A column (or expression) a list can be ordered by. Ties are broken by `id`.
+pub trait SortKey: Copy + PartialEq + Send + 'static {
+    const ALL: &'static [Self];
+    /// Used when the request has no `sort` parameter.
+    const DEFAULT: Sort<Self>;
+
+    /// Name in the `sort` parameter.
+    fn name(self) -> &'static str;
+    /// SQL expression rows are ordered by. Must never be NULL, so it can be compared with a cursor.
+    fn expression(self) -> &'static str;
+    /// Postgres type of `expression`, used to cast the cursor value back.
+    fn sql_type(self) -> &'static str;
+}
+
+/// Whether the cursor `value` casts to `sql_type`, so an edited cursor is rejected with
+/// 400 instead of failing in Postgres. Timestamps are in Postgres' text output format.
+fn casts_to(sql_type: &str, value: &str) -> bool {
+    match sql_type {
+        "timestamptz" => DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z").is_ok(),
+        "text" => true,
+        other => unreachable!("no cursor check for sort type {}", other),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sort<K> {
+    pub key: K,
+    pub descending: bool,
+}
+
+impl<K: SortKey> Sort<K> {
+    /// Parses `name` or `-name` (descending).
+    pub fn parse(value: &str) -> Result<Self, ListQueryError> {
+        let (name, descending) = match value.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (value, false),
+        };
+        let key = K::ALL.iter().copied().find(|key| key.name() == name).ok_or_else(|| ListQueryError::UnknownSortKey {
+            given: name.to_string(),
+            expected: K::ALL.iter().map(|key| key.name()).collect::<Vec<_>>().join(", "),
+        })?;
+        Ok(Sort { key, descending })
+    }
+
+    fn param(&self) -> String {
+        format!("{}{}", if self.descending { "-" } else { "" }, self.key.name())
+    }
+}
+
+/// Raw list parameters, as sent in the query string.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ListParams {
+    /// Sort key, prefixed with `-` for descending order.
+    pub sort: Option<String>,
+    /// Page size, 1 to 200 (default 50).
+    pub limit: Option<u32>,
+    /// Rows to skip. Cannot be combined with `cursor`.
+    pub offset: Option<u64>,
+    /// `next_cursor` from the previous page; only valid with the same `sort`.
+    pub cursor: Option<String>,
+}
+
+/// Where a page starts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Start {
+    Offset(u64),
+    After(Cursor),
+}
+
+/// Sort value and id of the last row of a page, so the next page can continue after
+/// it even if rows are inserted or deleted meanwhile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    sort: String,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(value).ok()?).ok()
+    }
+}
+
+/// Validated list parameters. Also an extractor, rejecting bad parameters with 400.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery<K> {
+    pub sort: Sort<K>,
+    pub limit: u32,
+    pub start: Start,
+}
+
+impl<K: SortKey> ListQuery<K> {
+    pub fn parse(params: ListParams) -> Result<Self, ListQueryError> {
+        let sort = match params.sort.as_deref() {
+            Some(value) => Sort::parse(value)?,
+            None => K::DEFAULT,
+        };
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(ListQueryError::InvalidLimit);
+        }
+        let start = match (params.offset, params.cursor) {
+            (Some(_), Some(_)) => return Err(ListQueryError::OffsetWithCursor),
+            (_, Some(cursor)) => match Cursor::decode(&cursor) {
+                Some(cursor) if cursor.sort == sort.param() && casts_to(sort.key.sql_type(), &cursor.value) => {
+                    Start::After(cursor)
+                }
+                _ => return Err(ListQueryError::InvalidCursor),
+            },
+            (offset, None) => Start::Offset(offset.unwrap_or(0)),
+        };
+        Ok(ListQuery { sort, limit, start })
+    }
+
+    /// Select-list entry exposing the sort value as `sort_value`, for `build_page`.
+    pub fn sort_value_column(&self) -> String {
+        format!("({})::text AS sort_value", self.sort.key.expression())
+    }
+
+    /// Appends the cursor condition, `ORDER BY`, `LIMIT` and `OFFSET`. The query must
+    /// already have a `WHERE` clause. One extra row is fetched to tell whether another
+    /// page follows.
+    pub fn push_page(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let expression = self.sort.key.expression();
+        let direction = if self.sort.descending { "DESC" } else { "ASC" };
+        if let Start::After(cursor) = &self.start {
+            let comparison = if self.sort.descending { "<" } else { ">" };
+            builder
+                .push(format_args!(" AND ({expression}, id) {comparison} (CAST("))
+                .push_bind(cursor.value.clone())
+                .push(format_args!(" AS {}), ", self.sort.key.sql_type()))
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        builder
+            .push(format_args!(" ORDER BY {expression} {direction}, id {direction} LIMIT "))
+            .push_bind(i64::from(self.limit) + 1);
+        if let Start::Offset(offset) = self.start {
+            builder.push(" OFFSET ").push_bind(offset as i64);
+        }
+    }
+
+    /// Builds the page from rows fetched with `push_page`, each paired with its `sort_value`.
+    pub fn build_page<T>(&self, mut rows: Vec<(T, String)>, total: i64, id: impl Fn(&T) -> Uuid) -> Page<T> {
+        let has_more = rows.len() > self.limit as usize;
+        rows.truncate(self.limit as usize);
+        let next_cursor = match rows.last() {
+            Some((item, value)) if has_more => {
+                Some(Cursor { sort: self.sort.param(), value: value.clone(), id: id(item) }.encode())
+            }
+            _ => None,
+        };
+        Page { items: rows.into_iter().map(|(item, _)| item).collect(), total, next_cursor }
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync, K: SortKey> FromRequestParts<S> for ListQuery<K> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<ListParams>::try_from_uri(&parts.uri)
+            .map_err(|e| AppError::bad_request("invalid_list_query", e.body_text()))?;
+        Ok(ListQuery::parse(params)?)
+    }
+}
+
+/// One page of a list.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Rows matching the filters, across all pages.
+    pub total: i64,
+    /// Pass as `cursor` to fetch the next page; `null` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// `ILIKE` pattern matching `value` anywhere, with `%`, `_` and `\` taken literally.
+pub fn contains_pattern(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum TestSort {
+        CreatedAt,
+        Name,
+    }
+
+    impl SortKey for TestSort {
+        const ALL: &'static [Self] = &[TestSort::CreatedAt, TestSort::Name];
+        const DEFAULT: Sort<Self> = Sort { key: TestSort::CreatedAt, descending: true };
+
+        fn name(self) -> &'static str {
+            match self {
+                TestSort::CreatedAt => "created_at",
+                TestSort::Name => "name",
+            }
+        }
+
+        fn expression(self) -> &'static str {
+            match self {
+                TestSort::CreatedAt => "created_at",
+                TestSort::Name => "lower(name)",
+            }
+        }
+
+        fn sql_type(self) -> &'static str {
+            match self {
+                TestSort::CreatedAt => "timestamptz",
+                TestSort::Name => "text",
+            }
+        }
+    }
+
+    fn parse(query: &str) -> Result<ListQuery<TestSort>, ListQueryError> {
+        ListQuery::parse(params(query))
+    }
+
+    fn params(query: &str) -> ListParams {
+        let uri: axum::http::Uri = format!("/?{}", query).parse().unwrap();
+        Query::<ListParams>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_parses_sort_limit_and_offset() {
+        let query = parse("").unwrap();
+        assert_eq!((query.sort, query.limit, query.start), (TestSort::DEFAULT, DEFAULT_LIMIT, Start::Offset(0)));
+
+        let query = parse("sort=name&limit=10&offset=20").unwrap();
+        assert_eq!(query.sort, Sort { key: TestSort::Name, descending: false });
+        assert_eq!((query.limit, query.start), (10, Start::Offset(20)));
+
+        assert_eq!(
+            parse("sort=-email").unwrap_err(),
+            ListQueryError::UnknownSortKey { given: "email".to_string(), expected: "created_at, name".to_string() }
+        );
+        assert_eq!(parse("limit=0").unwrap_err(), ListQueryError::InvalidLimit);
+        assert_eq!(parse("limit=201").unwrap_err(), ListQueryError::InvalidLimit);
+    }
+
+    #[test]
+    fn test_cursor_continues_the_same_sort() {
+        let query = parse("sort=-name&limit=2").unwrap();
+        let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let rows = ids.iter().map(|id| (*id, format!("name {}", id))).collect();
+        let page = query.build_page(rows, 7, |id| *id);
+        assert_eq!((page.items, page.total), (ids[..2].to_vec(), 7));
+
+        let cursor = page.next_cursor.unwrap();
+        let next = parse(&format!("sort=-name&limit=2&cursor={}", cursor)).unwrap();
+        assert_eq!(
+            next.start,
+            Start::After(Cursor { sort: "-name".to_string(), value: format!("name {}", ids[1]), id: ids[1] })
+        );
+        // The last page has no cursor.
+        assert!(next.build_page(vec![(ids[2], String::new())], 7, |id| *id).next_cursor.is_none());
+
+        assert_eq!(parse(&format!("sort=name&cursor={}", cursor)).unwrap_err(), ListQueryError::InvalidCursor);
+        assert_eq!(parse(&format!("offset=2&cursor={}", cursor)).unwrap_err(), ListQueryError::OffsetWithCursor);
+        assert_eq!(parse("cursor=not-a-cursor").unwrap_err(), ListQueryError::InvalidCursor);
+    }
+
+    #[test]
+    fn test_cursor_value_must_cast_to_the_sort_type() {
+        let cursor = |value: &str| Cursor { sort: "-created_at".to_string(), value: value.to_string(), id: Uuid::nil() }.encode();
+        for value in ["2026-10-18 19:34:22.808785+00", "2026-10-18 19:34:22+05:30"] {
+            assert!(matches!(parse(&format!("cursor={}", cursor(value))).unwrap().start, Start::After(_)), "{}", value);
+        }
+        for value in ["x", "", "2026-10-18"] {
+            assert_eq!(parse(&format!("cursor={}", cursor(value))).unwrap_err(), ListQueryError::InvalidCursor, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern(r"50%_off\"), r"%50\%\_off\\%");
+    }
+}
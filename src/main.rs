@@ -5,64 +5,223 @@ use axum::{
     serve,
     middleware,
 };
-use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tower_http::services::ServeDir;
+
+use crate::state::AppState;
 
 // AI: Declare modules according to project structure
 mod auth;
+mod avatar;
+mod config;
 mod db;
+mod error;
+mod layers;
+mod listing;
+mod metrics;
+mod openapi;
+mod rate_limit;
+mod request_id;
 mod routes; // Added routes module
+mod server;
+mod shutdown;
+mod state;
+mod storage;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod validation;
+mod webhooks;
+
+/// How long shutdown waits for database connections still in use after the drain.
+const POOL_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok(); // Load .env file if it exists
 
-    // AI: Initialize tracing/logging as per Phase 4.2
+    // All settings are read and validated once; every problem is reported before exiting.
+    // Logging is configured by the same file, so this error goes to stderr directly.
+    let config = match config::AppConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}. Exiting.", e);
+            std::process::exit(1);
+        }
+    };
+    telemetry::init_tracing(&config.logging);
+    for warning in config.warnings() {
+        tracing::warn!(%warning, "Check the configuration");
+    }
 
     // Initialize database pool
-    // AI: Proper error handling. For now, panicking if DB pool creation fails.
-    let db_pool = match db::init_db_pool().await {
+    let db_pool = match db::init_db_pool(&config.database).await {
         Ok(pool) => {
-            println!("Successfully connected to the database and created pool.");
-            // Optional: Test the connection
-            // if let Err(e) = db::test_db_connection(&pool).await {
-            //     eprintln!("Failed to test database connection: {}. Exiting.", e);
-            //     std::process::exit(1);
-            // }
+            tracing::info!("Connected to the database");
+            if let Err(e) = db::test_db_connection(&pool).await {
+                tracing::error!(error = %e, "Failed to test database connection. Exiting.");
+                std::process::exit(1);
+            }
             pool
         }
         Err(e) => {
-            eprintln!("Failed to initialize database pool: {}. Exiting.", e);
+            tracing::error!(error = %e, "Failed to initialize database pool. Exiting.");
             std::process::exit(1);
         }
     };
 
-    // Fetch and cache JWKS on startup
-    // AI: Proper error handling should be implemented here. 
-    // For now, panicking if JWKS fetching fails.
-    match auth::jwks::get_jwks().await {
-        Ok(_) => println!("Successfully fetched and cached JWKS."),
-        Err(e) => {
-            eprintln!("Failed to fetch JWKS: {}. Exiting.", e);
-            // AI: In a real app, consider more graceful shutdown or retry logic.
-            std::process::exit(1);
+    let mut state = AppState::new(db_pool, config);
+
+    // Profile metadata is checked against the configured JSON Schema; a bad schema fails startup.
+    if let Some(path) = &state.config.profiles.metadata_schema {
+        match validation::load_metadata_schema(path) {
+            Ok(schema) => {
+                tracing::info!(schema = %path.display(), "Validating profile metadata");
+                state.profile_validator = Arc::new(validation::ProfileValidator::new(
+                    Some(schema),
+                    state.config.profiles.metadata_max_bytes,
+                ));
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to load profile metadata schema. Exiting.");
+                std::process::exit(1);
+            }
         }
     }
 
+    // Load signing keys from the configured JWKS source. A URL source is fetched in the
+    // background with retries (falling back to the last-known-good snapshot); protected
+    // routes answer 503 until keys are available.
+    if let Err(e) = auth::jwks::init(&state.config.auth, &state.keys, &state.http, &state.metrics, &state.shutdown) {
+        tracing::error!(error = %e, "Failed to load JWKS. Exiting.");
+        std::process::exit(1);
+    }
+
+    // The admin user management routes need the service role key; run without them if it is absent.
+    if state.gotrue.is_none() {
+        tracing::info!("Admin user management disabled: SUPABASE_URL and SUPABASE_SERVICE_ROLE_KEY not set");
+    }
+
+    // One limiter is shared by all routes: keyed by user id under /api, by client IP elsewhere.
+    tokio::spawn(state.shutdown.clone().run_until(state.rate_limiter.clone().run_cleanup()));
+
+    // SIGTERM/SIGINT flips readiness to failing and stops the background tasks above.
+    let shutdown = state.shutdown.clone();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown::signal().await;
+            shutdown.trigger();
+        }
+    });
+
     // Build application with routes
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(handler)) // Public route
-        // Group all /api routes and protect them with JWT auth middleware
-        .nest("/api", routes::app_routes(db_pool.clone()) // Pass db_pool here
-            .route_layer(middleware::from_fn(auth::middleware::jwt_auth_middleware))
-        );
-        // .layer(Extension(db_pool)); // AI: Removed as PgPool is now passed via with_state in app_routes
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    // AI: Read address from environment variable if available using std::env::var, e.g. for PORT
-    println!("Listening on {}", addr);
-    let listener = TcpListener::bind(addr).await.unwrap();
-    serve(listener, app).await.unwrap();
+        // The OpenAPI document (and Swagger UI in `dev` builds) is public.
+        .merge(openapi::openapi_routes())
+        // `layer` only wraps the routes added so far, so this IP-based limit covers the public
+        // routes above but not /api (limited per user) or the signed Supabase callbacks.
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit::rate_limit_middleware))
+        // Orchestrator probes, added after the limit so frequent polling is never throttled.
+        .merge(routes::health_routes::health_routes())
+        // All /api routes are protected by the JWT auth middleware (see `routes::app_routes`).
+        .nest("/api", routes::app_routes(&state));
+
+    // Uploaded avatars, when stored locally. Not rate limited: a page can show many avatars at once.
+    if state.config.storage.backend == config::StorageBackend::Local {
+        app = app.nest_service("/uploads", ServeDir::new(&state.config.storage.local_dir));
+    }
+
+    // Supabase Auth hooks authenticate with a signature, so they live outside the JWT-protected /api tree.
+    // Both are only mounted when their secret is configured.
+    if state.hook_verifier.is_some() {
+        app = app.nest("/hooks", routes::hook_routes::hook_routes());
+    }
+    if let Some(secret) = state.config.webhooks.secret_bytes() {
+        let registry = webhooks::handlers::default_registry(state.pool.clone());
+        let verifier = webhooks::WebhookVerifier::new(secret, state.config.webhooks.tolerance());
+        app = app.nest("/webhooks", routes::webhook_routes::webhook_routes(registry, verifier));
+    }
+
+    // Served on a separate admin port; a failure here does not take the API down.
+    if state.config.metrics.enabled {
+        let metrics_addr = state.config.metrics.addr();
+        match TcpListener::bind(metrics_addr).await {
+            Ok(listener) => {
+                tracing::info!(addr = %metrics_addr, "Serving metrics");
+                let metrics_app = metrics::metrics_routes().with_state(state.clone());
+                let stopped = state.shutdown.clone();
+                tokio::spawn(async move {
+                    let server = serve(listener, metrics_app).with_graceful_shutdown(async move { stopped.wait().await });
+                    if let Err(e) = server.await {
+                        tracing::error!(error = %e, "Metrics listener failed");
+                    }
+                });
+            }
+            Err(e) => tracing::error!(addr = %metrics_addr, error = %e, "Failed to bind metrics listener"),
+        }
+    }
+
+    // TLS (if configured) covers every TCP bind; the certificate is reloaded from disk when it changes.
+    let tls = match server::tls::init(&state.config.server.tls, &state.shutdown) {
+        Ok(tls) => tls,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load TLS certificate. Exiting.");
+            std::process::exit(1);
+        }
+    };
+    let mut listeners = Vec::new();
+    for addr in state.config.server.bind_addrs() {
+        match server::Listener::bind(&addr).await {
+            Ok(listener) => {
+                tracing::info!(%addr, tls = tls.is_some() && matches!(addr, config::BindAddr::Tcp(_)), "Listening");
+                listeners.push(listener);
+            }
+            Err(e) => {
+                tracing::error!(%addr, error = %e, "Failed to bind. Exiting.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let pool = state.pool.clone();
+    let shutdown_delay = state.config.server.shutdown_delay();
+    let shutdown_timeout = state.config.server.shutdown_timeout();
+    // Timeouts and metrics run after routing so they can use the route template; metrics
+    // wrap the timeout so timed-out requests are counted. The request id layer is
+    // outermost, so the trace span and every response (including errors) carry the request id.
+    let app = app
+        .layer(middleware::from_fn_with_state(state.config.clone(), layers::timeout_middleware))
+        .layer(middleware::from_fn_with_state(state.metrics.clone(), metrics::http_metrics_middleware));
+    let app = layers::apply(app, &state.config)
+        .layer(telemetry::trace_layer())
+        .layer(middleware::from_fn(request_id::request_id_middleware))
+        .with_state(state);
+
+    // Once shutdown is triggered, keep accepting for `shutdown_delay` while load balancers
+    // notice the failing readiness, then close the listeners and let in-flight requests finish.
+    let stop_accepting = {
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown.wait().await;
+            tracing::info!(delay = ?shutdown_delay, "Shutting down; readiness is now failing");
+            tokio::time::sleep(shutdown_delay).await;
+            tracing::info!(timeout = ?shutdown_timeout, "Closing listeners; draining in-flight requests");
+        }
+    };
+
+    // TCP connections carry `ConnectInfo`, which provides the client IP for rate limiting unauthenticated routes.
+    server::serve(listeners, app, tls, stop_accepting, shutdown_timeout).await;
+
+    // Aborted requests have returned their connections; anything else still holding one
+    // (such as a task spawned by a handler) must not keep the process alive.
+    if tokio::time::timeout(POOL_CLOSE_TIMEOUT, pool.close()).await.is_err() {
+        tracing::warn!(timeout = ?POOL_CLOSE_TIMEOUT, "Database connections still in use; exiting without closing them");
+    }
+    tracing::info!("Shutdown complete");
 }
 
 async fn handler() -> Html<&'static str> {
@@ -0,0 +1,195 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use prometheus::{
+    Encoder, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Prometheus metrics for the service, kept in their own registry.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    auth_failures: IntCounterVec,
+    jwks_refreshes: IntCounterVec,
+    jwks_key_age: Gauge,
+    pool_connections: IntGauge,
+    pool_idle: IntGauge,
+    tx_acquire: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let http_labels = &["method", "route", "status"];
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by route and status"),
+                http_labels,
+            )
+            .expect("valid metric"),
+            http_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and status"),
+                http_labels,
+            )
+            .expect("valid metric"),
+            auth_failures: IntCounterVec::new(
+                Opts::new("auth_failures_total", "Rejected authentications by AuthError kind"),
+                &["kind"],
+            )
+            .expect("valid metric"),
+            jwks_refreshes: IntCounterVec::new(
+                Opts::new("jwks_refresh_total", "JWKS fetch and reload attempts by result"),
+                &["result"],
+            )
+            .expect("valid metric"),
+            jwks_key_age: Gauge::new("jwks_key_age_seconds", "Seconds since the signing keys were last loaded")
+                .expect("valid metric"),
+            pool_connections: IntGauge::new("db_pool_connections", "Open database connections")
+                .expect("valid metric"),
+            pool_idle: IntGauge::new("db_pool_idle_connections", "Idle database connections").expect("valid metric"),
+            tx_acquire: Histogram::with_opts(HistogramOpts::new(
+                "db_tx_acquire_seconds",
+                "Time request transactions (Tx) waited for a pooled connection; handlers querying the pool directly are not included",
+            ))
+            .expect("valid metric"),
+            registry,
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_duration.clone()),
+            Box::new(metrics.auth_failures.clone()),
+            Box::new(metrics.jwks_refreshes.clone()),
+            Box::new(metrics.jwks_key_age.clone()),
+            Box::new(metrics.pool_connections.clone()),
+            Box::new(metrics.pool_idle.clone()),
+            Box::new(metrics.tx_acquire.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("metric names are unique");
+        }
+        metrics
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration.with_label_values(&labels).observe(elapsed.as_secs_f64());
+    }
+
+    pub fn auth_failure(&self, kind: &str) {
+        self.auth_failures.with_label_values(&[kind]).inc();
+    }
+
+    pub fn jwks_refresh(&self, success: bool) {
+        self.jwks_refreshes.with_label_values(&[if success { "success" } else { "failure" }]).inc();
+    }
+
+    #[cfg(test)]
+    pub fn jwks_refresh_count(&self, success: bool) -> u64 {
+        self.jwks_refreshes.with_label_values(&[if success { "success" } else { "failure" }]).get()
+    }
+
+    /// Only `Tx` reports here; pool contention also shows in `db_pool_idle_connections`.
+    pub fn observe_tx_acquire(&self, elapsed: Duration) {
+        self.tx_acquire.observe(elapsed.as_secs_f64());
+    }
+
+    /// Samples the gauges and encodes everything in the Prometheus text format.
+    fn render(&self, state: &AppState) -> Result<String, prometheus::Error> {
+        self.pool_connections.set(i64::from(state.pool.size()));
+        self.pool_idle.set(state.pool.num_idle() as i64);
+        if let Some(age) = state.keys.age() {
+            self.jwks_key_age.set(age.as_secs_f64());
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Records request count and latency. Applied with `Router::layer`, so it runs after
+/// routing and labels requests by route template (`/api/profiles/:user_id`), not by
+/// raw path, keeping label cardinality bounded.
+pub async fn http_metrics_middleware(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(req).await;
+    metrics.observe_request(&method, &route, response.status().as_u16(), started.elapsed());
+    response
+}
+
+/// Router for the admin listener, kept off the public port.
+pub fn metrics_routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics_handler))
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Result<Response, AppError> {
+    let body = state.metrics.render(&state).map_err(|e| AppError::internal("metrics_error", e))?;
+    let content_type = HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8");
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_labelled_by_route_and_status() {
+        let metrics = Metrics::new();
+        metrics.observe_request("GET", "/api/profiles/me", 200, Duration::from_millis(5));
+        metrics.auth_failure("token_expired");
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&metrics.registry.gather(), &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(r#"http_requests_total{method="GET",route="/api/profiles/me",status="200"} 1"#));
+        assert!(text.contains(r#"auth_failures_total{kind="token_expired"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn test_auth_failures_include_role_rejections() {
+        use crate::test_support::{authed, lazy_pool, test_config, test_state, token};
+        use axum::http::StatusCode;
+        use tower::ServiceExt;
+
+        let state = test_state(lazy_pool(), test_config());
+        let app = Router::new().nest("/api", crate::routes::app_routes(&state)).with_state(state.clone());
+        let id = uuid::Uuid::new_v4().to_string();
+        let uri = format!("/api/profiles/{}", id);
+
+        let response = app.clone().oneshot(authed("GET", &uri, &token(&id, Some("premium")), None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(authed("GET", &uri, "not-a-jwt", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let failures = |kind: &str| state.metrics.auth_failures.with_label_values(&[kind]).get();
+        assert_eq!(failures("forbidden"), 1);
+        assert_eq!(failures("invalid_token"), 1);
+    }
+}
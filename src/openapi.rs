@@ -0,0 +1,155 @@
+use axum::{routing::get, Json, Router};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use crate::routes::{admin_routes, echo_routes, health_routes, hook_routes, profile_routes, webhook_routes};
+use crate::state::AppState;
+
+/// The API description, generated from the handlers' `#[utoipa::path]` attributes and
+/// the request/response types. Role requirements are listed as scopes of `bearer_auth`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Supabase Axum API",
+        description = "Profiles and user management backed by Supabase Auth and Postgres. \
+            `/api` routes take a Supabase access token; where an operation lists `bearer_auth` \
+            scopes, the token's role must be one of them. Errors are `application/problem+json`."
+    ),
+    paths(
+        profile_routes::get_my_profile_handler,
+        profile_routes::create_my_profile_handler,
+        profile_routes::update_my_profile_handler,
+        profile_routes::patch_my_profile_handler,
+        profile_routes::delete_my_profile_handler,
+        profile_routes::upload_avatar_handler,
+        profile_routes::username_available_handler,
+        profile_routes::list_profiles_handler,
+        profile_routes::search_profiles_handler,
+        profile_routes::get_user_profile_handler,
+        profile_routes::update_user_profile_handler,
+        profile_routes::patch_user_profile_handler,
+        profile_routes::delete_user_profile_handler,
+        echo_routes::echo_handler,
+        echo_routes::premium_echo_handler,
+        admin_routes::list_users_handler,
+        admin_routes::create_user_handler,
+        admin_routes::delete_user_handler,
+        admin_routes::ban_user_handler,
+        admin_routes::unban_user_handler,
+        admin_routes::update_app_metadata_handler,
+        health_routes::healthz,
+        health_routes::readyz,
+        hook_routes::custom_access_token_handler,
+        webhook_routes::database_webhook_handler,
+        webhook_routes::auth_hook_handler,
+    ),
+    modifiers(&SecuritySchemes, &CommonApiResponses),
+    tags(
+        (name = "profiles", description = "The caller's profile, and other users' profiles for admins"),
+        (name = "echo", description = "Example endpoints for role checks"),
+        (name = "admin", description = "Auth user management (only mounted when the service role key is set)"),
+        (name = "health", description = "Orchestrator probes"),
+        (name = "hooks", description = "Supabase Auth hooks, authenticated by signature"),
+        (name = "webhooks", description = "Supabase database webhooks and auth hooks, authenticated by signature"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        // Standard Webhooks, as sent by Supabase Auth hooks.
+        components.add_security_scheme(
+            "standard_webhooks",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "webhook-signature",
+                "Standard Webhooks signature, with `webhook-id` and `webhook-timestamp`",
+            ))),
+        );
+        // The simpler HMAC scheme used by database webhooks.
+        components.add_security_scheme(
+            "webhook_signature",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "x-webhook-signature",
+                "Hex HMAC-SHA256 of `timestamp.body`, with `x-webhook-timestamp`",
+            ))),
+        );
+    }
+}
+
+/// Adds the errors every `/api` operation can return (from the auth middleware, the
+/// rate limiter and the database) so the handlers only document their own.
+struct CommonApiResponses;
+
+impl Modify for CommonApiResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let common = [
+            ("401", "Missing, invalid or expired access token"),
+            ("429", "Rate limit exceeded; see `Retry-After`"),
+            ("500", "Internal error"),
+            ("503", "Signing keys not loaded yet"),
+            ("504", "The request did not complete within its route's timeout (`request_timeout`)"),
+        ];
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !path.starts_with("/api/") {
+                continue;
+            }
+            let operations = [&mut item.get, &mut item.put, &mut item.post, &mut item.delete, &mut item.patch];
+            for operation in operations.into_iter().flatten() {
+                for (status, description) in common {
+                    operation.responses.responses.entry(status.to_string()).or_insert_with(|| {
+                        ResponseBuilder::new()
+                            .description(description)
+                            .content(
+                                "application/problem+json",
+                                ContentBuilder::new().schema(Some(Ref::from_schema_name("Problem"))).build(),
+                            )
+                            .build()
+                            .into()
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Serves the document at `/openapi.json`, and Swagger UI at `/docs` in `dev` builds.
+pub fn openapi_routes() -> Router<AppState> {
+    let doc = ApiDoc::openapi();
+    let router = Router::new().route("/openapi.json", get(move || async move { Json(doc) }));
+
+    #[cfg(feature = "dev")]
+    let router = router.merge(
+        utoipa_swagger_ui::SwaggerUi::new("/docs").config(utoipa_swagger_ui::Config::from("/openapi.json")),
+    );
+
+    router
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_covers_routes_and_security() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(doc["components"]["securitySchemes"]["bearer_auth"]["scheme"], "bearer");
+
+        let me = &doc["paths"]["/api/profiles/me"];
+        assert!(me["get"]["responses"]["200"].is_object());
+        let unauthorized = &me["get"]["responses"]["401"]["content"]["application/problem+json"];
+        assert_eq!(unauthorized["schema"]["$ref"], "#/components/schemas/Problem");
+        assert!(me["put"]["responses"]["504"]["description"].as_str().unwrap().contains("request_timeout"));
+        assert_eq!(doc["paths"]["/api/profiles/{user_id}"]["get"]["security"][0]["bearer_auth"][0], "admin");
+        assert!(doc["paths"]["/healthz"]["get"]["responses"]["401"].is_null());
+        assert!(doc["components"]["schemas"]["UserProfile"].is_object());
+    }
+}
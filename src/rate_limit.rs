@@ -0,0 +1,269 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::auth::user_context::{AuthUser, UserRole};
+use crate::error::AppError;
+
+/// Requests allowed per minute for one tier. The bucket holds a full minute's
+/// quota, so clients may burst up to `per_minute` requests at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct RateLimitTier {
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub user: RateLimitTier,
+    pub premium: RateLimitTier,
+    pub admin: RateLimitTier,
+    /// Applied per client IP on routes without an authenticated user.
+    pub anonymous: RateLimitTier,
+    /// Take the client IP from `X-Forwarded-For` (only behind a trusted proxy).
+    pub trust_proxy: bool,
+    /// Trusted proxies in front of the service, each appending to `X-Forwarded-For`. The
+    /// client IP is the entry this far from the right; entries further left are whatever
+    /// the client sent and cannot be trusted.
+    pub trusted_hops: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            user: RateLimitTier { per_minute: 60 },
+            premium: RateLimitTier { per_minute: 600 },
+            admin: RateLimitTier { per_minute: 1200 },
+            anonymous: RateLimitTier { per_minute: 30 },
+            trust_proxy: false,
+            trusted_hops: 1,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn tier_for(&self, role: UserRole) -> RateLimitTier {
+        match role {
+            UserRole::User => self.user,
+            UserRole::Premium => self.premium,
+            UserRole::Admin => self.admin,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    User(String),
+    Ip(IpAddr),
+}
+
+/// Outcome of a rate limit check, used to build the `RateLimit-*` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next request would be allowed (only when rejected).
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    capacity: f64,
+}
+
+/// In-memory token bucket limiter. Limits are per process; with several replicas
+/// each enforces its own quota.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<RateLimitKey, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes one token from the key's bucket if available.
+    pub fn check(&self, key: RateLimitKey, tier: RateLimitTier, now: Instant) -> RateLimitDecision {
+        let capacity = f64::from(tier.per_minute.max(1));
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(key).or_insert(Bucket { tokens: capacity, updated_at: now, capacity });
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.capacity = capacity;
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let retry_after_secs = if allowed { 0 } else { ((1.0 - bucket.tokens) / refill_per_sec).ceil() as u64 };
+
+        RateLimitDecision {
+            allowed,
+            limit: tier.per_minute,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: ((capacity - bucket.tokens) / refill_per_sec).ceil() as u64,
+            retry_after_secs,
+        }
+    }
+
+    /// Drops buckets that have refilled completely; they are indistinguishable from new ones.
+    pub fn prune(&self, now: Instant) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+            bucket.tokens + elapsed * bucket.capacity / 60.0 < bucket.capacity
+        });
+    }
+
+    /// Background task that keeps the bucket map from growing without bound.
+    pub async fn run_cleanup(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            self.prune(Instant::now());
+        }
+    }
+
+    fn client_ip(&self, req: &Request) -> Option<IpAddr> {
+        if self.config.trust_proxy
+            && let Some(ip) = forwarded_for(req.headers(), self.config.trusted_hops)
+        {
+            return Some(ip);
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+}
+
+/// The address the outermost of `hops` trusted proxies saw the request come from. `None`
+/// when the header has fewer entries, i.e. the request did not pass through every proxy.
+fn forwarded_for(headers: &HeaderMap, hops: usize) -> Option<IpAddr> {
+    let mut entries = Vec::new();
+    for value in headers.get_all("x-forwarded-for") {
+        entries.extend(value.to_str().ok()?.split(','));
+    }
+    entries.len().checked_sub(hops).and_then(|index| entries[index].trim().parse().ok())
+}
+
+/// Rate limits by `AuthUser.id` with the tier of the user's role, or by client IP
+/// for requests without an authenticated user. Must run inside the auth middleware
+/// for user-based limits to apply.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !limiter.config.enabled {
+        return next.run(req).await;
+    }
+
+    let (key, tier) = match req.extensions().get::<AuthUser>() {
+        Some(user) => (RateLimitKey::User(user.id.clone()), limiter.config.tier_for(user.role)),
+        None => match limiter.client_ip(&req) {
+            Some(ip) => (RateLimitKey::Ip(ip), limiter.config.anonymous),
+            // Without a user or an address there is nothing sensible to key on.
+            None => return next.run(req).await,
+        },
+    };
+
+    let decision = limiter.check(key, tier, Instant::now());
+    let mut response = if decision.allowed {
+        next.run(req).await
+    } else {
+        AppError::TooManyRequests { retry_after_secs: decision.retry_after_secs }.into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(decision.reset_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        let tier = RateLimitTier { per_minute: 2 };
+        RateLimiter::new(RateLimitConfig {
+            enabled: true,
+            user: tier,
+            premium: RateLimitTier { per_minute: 10 },
+            admin: tier,
+            anonymous: tier,
+            trust_proxy: false,
+            trusted_hops: 1,
+        })
+    }
+
+    #[test]
+    fn test_bucket_rejects_when_empty_and_refills() {
+        let limiter = limiter();
+        let tier = RateLimitTier { per_minute: 2 };
+        let key = RateLimitKey::User("user-1".into());
+        let start = Instant::now();
+
+        assert!(limiter.check(key.clone(), tier, start).allowed);
+        let second = limiter.check(key.clone(), tier, start);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let rejected = limiter.check(key.clone(), tier, start);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.retry_after_secs, 30);
+
+        // One token refills every 30 seconds at 2 requests per minute.
+        assert!(limiter.check(key, tier, start + Duration::from_secs(30)).allowed);
+    }
+
+    #[test]
+    fn test_keys_and_tiers_are_independent() {
+        let limiter = limiter();
+        let now = Instant::now();
+        let premium = limiter.config.tier_for(UserRole::Premium);
+        for _ in 0..10 {
+            assert!(limiter.check(RateLimitKey::User("premium".into()), premium, now).allowed);
+        }
+        let ip = RateLimitKey::Ip("127.0.0.1".parse().unwrap());
+        assert!(limiter.check(ip, limiter.config.anonymous, now).allowed);
+
+        limiter.prune(now + Duration::from_secs(60));
+        assert!(limiter.buckets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_forwarded_for_ignores_client_supplied_entries() {
+        let mut headers = HeaderMap::new();
+        // The client sent "1.1.1.1, 2.2.2.2"; the load balancer appended the address it saw,
+        // then the ingress appended the load balancer's.
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.1.1.1, 2.2.2.2, 203.0.113.7"));
+        headers.append("x-forwarded-for", HeaderValue::from_static("10.0.0.2"));
+
+        assert_eq!(forwarded_for(&headers, 1), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(forwarded_for(&headers, 2), Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(forwarded_for(&headers, 5), None);
+        assert_eq!(forwarded_for(&HeaderMap::new(), 1), None);
+    }
+}
@@ -0,0 +1,425 @@
+use axum::{
+    extract::{FromRequestParts, State},
+    http::{request::Parts, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::gotrue::{AuthAdminUser, AuthUserPage, GoTrueAdminClient, GoTrueError, NewAuthUser, PERMANENT_BAN_DURATION};
+use crate::auth::user_context::{AdminUser, UserRole};
+use crate::db::models::{CreateProfilePayload, UserProfile};
+use crate::db::{profile_repository, user_role_repository};
+use crate::db::DbError;
+use crate::error::{AppError, Problem};
+use crate::routes::extract::{Json, Query};
+use crate::routes::path::UserIdPath;
+use crate::state::AppState;
+use crate::validation::ProfileValidator;
+
+/// The admin API client from `AppState`. The admin routes are only mounted when it
+/// is configured, so the rejection is a safety net.
+#[axum::async_trait]
+impl FromRequestParts<AppState> for GoTrueAdminClient {
+    type Rejection = GoTrueError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state.gotrue.clone().ok_or(GoTrueError::NotConfigured)
+    }
+}
+
+/// Admin-only routes for managing auth users. Every handler requires the `Admin` role.
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list_users_handler))
+        .route("/users", post(create_user_handler))
+        .route("/users/:user_id", delete(delete_user_handler))
+        .route("/users/:user_id/ban", post(ban_user_handler))
+        .route("/users/:user_id/unban", post(unban_user_handler))
+        .route("/users/:user_id/app_metadata", put(update_app_metadata_handler))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListUsersQuery {
+    /// 1-based page number (default 1).
+    pub page: Option<u32>,
+    /// Page size, 1 to 1000 (default 50).
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateUserPayload {
+    pub email: String,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub email_confirm: bool,
+    pub role: Option<UserRole>,
+    pub username: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub user_metadata: Value,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BanUserPayload {
+    /// Ban duration in GoTrue format, e.g. "24h". Defaults to an indefinite ban.
+    pub duration: Option<String>,
+}
+
+/// New `app_metadata` keys. `role` is validated against `UserRole`; other keys are passed through.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAppMetadataPayload {
+    pub role: Option<UserRole>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminUserResponse {
+    pub user: AuthAdminUser,
+    pub profile: Option<UserProfile>,
+}
+
+/// Lists auth users, paginated by GoTrue (`page` is 1-based).
+#[utoipa::path(
+    get,
+    path = "/api/admin/users",
+    tag = "admin",
+    params(ListUsersQuery),
+    responses(
+        (status = 200, description = "One page of auth users", body = AuthUserPage),
+        (status = 400, description = "`page` or `per_page` is not a number (`invalid_query`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn list_users_handler(
+    _admin: AdminUser,
+    gotrue: GoTrueAdminClient,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<AuthUserPage>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 1000);
+    let users = gotrue.list_users(page, per_page).await?;
+    Ok(Json(users))
+}
+
+/// Creates an auth user, its profile and (if given) its role. If the local rows cannot be
+/// created the auth user is deleted again so the two never drift apart.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users",
+    tag = "admin",
+    request_body = CreateUserPayload,
+    responses(
+        (status = 201, description = "Auth user and profile created", body = AdminUserResponse),
+        (status = 409, description = "The username is taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid username (`validation_failed`) or rejected by the auth service (`auth_request_rejected`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn create_user_handler(
+    _admin: AdminUser,
+    State(pool): State<PgPool>,
+    State(validator): State<Arc<ProfileValidator>>,
+    gotrue: GoTrueAdminClient,
+    Json(payload): Json<CreateUserPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    // Checked before the auth user exists, so a bad username does not need a rollback.
+    let profile_payload = CreateProfilePayload { username: payload.username, ..Default::default() };
+    validator.check_create(&profile_payload)?;
+
+    let app_metadata = match payload.role {
+        Some(role) => serde_json::json!({ "role": role }),
+        None => Value::Null,
+    };
+    let new_user = NewAuthUser {
+        email: payload.email,
+        password: payload.password,
+        email_confirm: payload.email_confirm,
+        app_metadata,
+        user_metadata: payload.user_metadata,
+    };
+    let user = gotrue.create_user(&new_user).await?;
+
+    // Role and profile are written atomically; on failure neither row is left behind.
+    let local_rows = async {
+        let mut tx = pool.begin().await?;
+        if let Some(role) = payload.role {
+            user_role_repository::upsert_user_role(&mut *tx, user.id, role).await?;
+        }
+        let profile = profile_repository::create_profile(&mut *tx, user.id, user.email.clone(), profile_payload).await?;
+        tx.commit().await?;
+        Ok::<_, DbError>(profile)
+    };
+    let profile = match local_rows.await {
+        Ok(profile) => profile,
+        Err(e) => {
+            if let Err(cleanup_err) = gotrue.delete_user(user.id).await {
+                tracing::error!(user_id = %user.id, error = %cleanup_err, "Failed to roll back auth user after profile creation error");
+            }
+            return Err(e.into());
+        }
+    };
+
+    Ok((StatusCode::CREATED, Json(AdminUserResponse { user, profile: Some(profile) })))
+}
+
+/// Deletes an auth user together with their profile.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/users/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 404, description = "No such auth user (`user_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn delete_user_handler(
+    AdminUser(admin): AdminUser,
+    State(pool): State<PgPool>,
+    gotrue: GoTrueAdminClient,
+    UserIdPath(user_id): UserIdPath,
+) -> Result<StatusCode, AppError> {
+    // Delete the auth user first: if that fails the profile is still intact.
+    gotrue.delete_user(user_id).await?;
+    tracing::info!(admin_id = %admin.id, %user_id, "Admin deleted auth user");
+
+    // `profiles.id` cascades from `auth.users`, so the row is normally gone already.
+    match profile_repository::delete_profile(&pool, user_id).await {
+        Ok(()) | Err(DbError::ProfileNotFound) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Bans a user for `duration`, or indefinitely when no body is sent.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{user_id}/ban",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    request_body(content = Option<BanUserPayload>, description = "Optional ban duration"),
+    responses(
+        (status = 200, description = "The banned user", body = AdminUserResponse),
+        (status = 404, description = "No such auth user (`user_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn ban_user_handler(
+    AdminUser(admin): AdminUser,
+    State(pool): State<PgPool>,
+    gotrue: GoTrueAdminClient,
+    UserIdPath(user_id): UserIdPath,
+    payload: Option<Json<BanUserPayload>>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let duration = payload
+        .as_ref()
+        .and_then(|Json(p)| p.duration.as_deref())
+        .unwrap_or(PERMANENT_BAN_DURATION);
+    let user = gotrue.set_ban_duration(user_id, duration).await?;
+    tracing::info!(admin_id = %admin.id, %user_id, %duration, "Admin banned auth user");
+    with_profile(&pool, user).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{user_id}/unban",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    responses(
+        (status = 200, description = "The unbanned user", body = AdminUserResponse),
+        (status = 404, description = "No such auth user (`user_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn unban_user_handler(
+    _admin: AdminUser,
+    State(pool): State<PgPool>,
+    gotrue: GoTrueAdminClient,
+    UserIdPath(user_id): UserIdPath,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = gotrue.set_ban_duration(user_id, "none").await?;
+    with_profile(&pool, user).await
+}
+
+/// Merges keys into the user's `app_metadata`. Role changes take effect when the
+/// user's next access token is issued.
+#[utoipa::path(
+    put,
+    path = "/api/admin/users/{user_id}/app_metadata",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    request_body = UpdateAppMetadataPayload,
+    responses(
+        (status = 200, description = "The updated user", body = AdminUserResponse),
+        (status = 404, description = "No such auth user (`user_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn update_app_metadata_handler(
+    _admin: AdminUser,
+    State(pool): State<PgPool>,
+    gotrue: GoTrueAdminClient,
+    UserIdPath(user_id): UserIdPath,
+    Json(payload): Json<UpdateAppMetadataPayload>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let mut app_metadata = payload.extra;
+    if let Some(role) = payload.role {
+        app_metadata.insert("role".to_string(), Value::String(role.to_string()));
+    }
+    // GoTrue goes first: it reports unknown users as 404, and if it fails the role the
+    // access token hook issues is left as it was.
+    let user = gotrue.update_app_metadata(user_id, Value::Object(app_metadata)).await?;
+    if let Some(role) = payload.role {
+        // `public.user_roles` is what the access token hook reads; app_metadata mirrors it.
+        user_role_repository::upsert_user_role(&pool, user_id, role).await?;
+    }
+    with_profile(&pool, user).await
+}
+
+async fn with_profile(pool: &PgPool, user: AuthAdminUser) -> Result<Json<AdminUserResponse>, AppError> {
+    let profile = match profile_repository::get_profile_by_id(pool, user.id).await {
+        Ok(profile) => Some(profile),
+        Err(DbError::ProfileNotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Json(AdminUserResponse { user, profile }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use serde_json::json;
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use crate::test_support::{
+        authed, json_body, lazy_pool, mock_gotrue, test_config, test_pool, test_state, test_user, token, SERVICE_KEY,
+    };
+
+    fn app(pool: PgPool, gotrue_url: &str) -> Router {
+        let mut config = test_config();
+        config.auth.supabase_url = Some(gotrue_url.to_string());
+        config.auth.service_role_key = Some(SERVICE_KEY.to_string());
+        let state = test_state(pool, config);
+        Router::new().nest("/api", crate::routes::app_routes(&state)).with_state(state)
+    }
+
+    fn admin_token() -> String {
+        token(&Uuid::new_v4().to_string(), Some("admin"))
+    }
+
+    #[tokio::test]
+    async fn test_admin_role_is_required() {
+        let mock = mock_gotrue(Some("1")).await;
+        let app = app(lazy_pool(), &mock.url);
+        let id = Uuid::new_v4();
+        let requests = [
+            ("GET", "/api/admin/users".to_string(), None),
+            ("POST", "/api/admin/users".to_string(), Some(json!({ "email": "new@example.com" }))),
+            ("DELETE", format!("/api/admin/users/{}", id), None),
+            ("POST", format!("/api/admin/users/{}/ban", id), None),
+            ("POST", format!("/api/admin/users/{}/unban", id), None),
+            ("PUT", format!("/api/admin/users/{}/app_metadata", id), Some(json!({ "role": "admin" }))),
+        ];
+        for role in [None, Some("premium")] {
+            let token = token(&Uuid::new_v4().to_string(), role);
+            for (method, uri, body) in &requests {
+                let response = app.clone().oneshot(authed(method, uri, &token, body.clone())).await.unwrap();
+                assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {} as {:?}", method, uri, role);
+            }
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_returns_total() {
+        let mock = mock_gotrue(Some("42")).await;
+        let response = app(lazy_pool(), &mock.url)
+            .oneshot(authed("GET", "/api/admin/users?page=0&per_page=5000", &admin_token(), None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["total"], 42);
+        assert_eq!(body["users"].as_array().unwrap().len(), 1);
+        assert_eq!(mock.requests(), ["GET /auth/v1/admin/users?page=1&per_page=1000"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_rejects_bad_paging() {
+        let mock = mock_gotrue(None).await;
+        let app = app(lazy_pool(), &mock.url);
+        for query in ["page=first", "per_page=-1"] {
+            let response = app.clone().oneshot(authed("GET", &format!("/api/admin/users?{}", query), &admin_token(), None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+            assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "application/problem+json");
+            assert_eq!(json_body(response).await["code"], "invalid_query");
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_user_rolls_back_auth_user_when_profile_fails() {
+        // Nothing listens on port 1, so the local transaction fails after GoTrue created the user.
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_secs(1))
+            .connect_lazy("postgres://postgres@127.0.0.1:1/unused")
+            .unwrap();
+        let mock = mock_gotrue(None).await;
+        let body = json!({ "email": "new@example.com", "role": "premium" });
+        let response = app(pool, &mock.url).oneshot(authed("POST", "/api/admin/users", &admin_token(), Some(body))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2, "{:?}", requests);
+        assert_eq!(requests[0], "POST /auth/v1/admin/users");
+        assert!(requests[1].starts_with("DELETE /auth/v1/admin/users/"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_checks_username_before_calling_gotrue() {
+        let mock = mock_gotrue(None).await;
+        let body = json!({ "email": "new@example.com", "username": "a b" });
+        let response = app(lazy_pool(), &mock.url).oneshot(authed("POST", "/api/admin/users", &admin_token(), Some(body))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_app_metadata_of_unknown_user_is_not_found() {
+        // The mock answers 404 for updates; the database is never reached.
+        let mock = mock_gotrue(None).await;
+        let uri = format!("/api/admin/users/{}/app_metadata", Uuid::new_v4());
+        let body = json!({ "role": "premium" });
+        let response = app(lazy_pool(), &mock.url).oneshot(authed("PUT", &uri, &admin_token(), Some(body))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(json_body(response).await["code"], "user_not_found");
+    }
+
+    #[tokio::test]
+    async fn test_update_app_metadata_keeps_role_when_gotrue_fails() {
+        let Some(pool) = test_pool().await else { return };
+        let user_id = test_user(&pool).await;
+        user_role_repository::upsert_user_role(&pool, user_id, UserRole::Premium).await.unwrap();
+
+        // Nothing listens on port 1.
+        let uri = format!("/api/admin/users/{}/app_metadata", user_id);
+        let body = json!({ "role": "admin" });
+        let response = app(pool.clone(), "http://127.0.0.1:1").oneshot(authed("PUT", &uri, &admin_token(), Some(body))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        let record = user_role_repository::get_user_role(&pool, user_id).await.unwrap().unwrap();
+        assert_eq!(record.role, "premium");
+    }
+}
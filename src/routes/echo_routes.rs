@@ -1,45 +1,46 @@
 use axum::{
-    extract::Json,
-    http::StatusCode,
-    response::IntoResponse,
     routing::post,
     Router,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use utoipa::ToSchema;
 
 use crate::auth::user_context::{AuthUser, UserRole};
+use crate::error::{AppError, Problem};
+use crate::routes::extract::Json;
+use crate::state::AppState;
 
 /// Request payload for the echo endpoints
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct EchoRequest {
     pub message: String,
 }
 
 /// Response payload for the echo endpoints
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct EchoResponse {
     pub echoed_message: String,
     pub user_id: String,
     pub role: String,
 }
 
-/// Error response for unauthorized access
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    pub error: String,
-}
-
 /// Router for the echo endpoints
-pub fn echo_routes(pool: PgPool) -> Router {
+pub fn echo_routes() -> Router<AppState> {
     Router::new()
         .route("/echo", post(echo_handler))
         .route("/premium_echo", post(premium_echo_handler))
-        .with_state(pool)
 }
 
 /// Handler for the regular echo endpoint
 /// Accessible by any authenticated user
+#[utoipa::path(
+    post,
+    path = "/api/echo",
+    tag = "echo",
+    request_body = EchoRequest,
+    responses((status = 200, description = "The message, echoed back with the caller's identity", body = EchoResponse)),
+    security(("bearer_auth" = []))
+)]
 async fn echo_handler(
     auth_user: AuthUser, 
     Json(payload): Json<EchoRequest>,
@@ -55,18 +56,24 @@ async fn echo_handler(
 
 /// Handler for the premium echo endpoint
 /// Only accessible by users with a "premium" role
+#[utoipa::path(
+    post,
+    path = "/api/premium_echo",
+    tag = "echo",
+    request_body = EchoRequest,
+    responses(
+        (status = 200, description = "The message with a premium prefix", body = EchoResponse),
+        (status = 403, description = "Caller is neither premium nor admin (`premium_required`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["premium", "admin"]))
+)]
 async fn premium_echo_handler(
     auth_user: AuthUser,
     Json(payload): Json<EchoRequest>,
-) -> Result<Json<EchoResponse>, impl IntoResponse> {
+) -> Result<Json<EchoResponse>, AppError> {
     // Verify that the user has the premium role
     if auth_user.role != UserRole::Premium && auth_user.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: "Unauthorized: Premium or Admin role required".to_string(),
-            }),
-        ));
+        return Err(AppError::forbidden("premium_required", "Premium or Admin role required"));
     }
     
     // If authorized, return a premium-specific echo response
@@ -0,0 +1,167 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use crate::db;
+use crate::state::AppState;
+
+/// Upper bound for each database probe, well below typical orchestrator probe timeouts.
+const DB_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Router for the orchestrator probes. Public and not rate limited.
+pub fn health_routes() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
+
+/// Liveness: the process is up and serving requests. Deliberately checks nothing
+/// else, so a database outage does not get the pod restarted.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is up", body = Object, example = json!({ "status": "ok" })))
+)]
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Readiness {
+    /// `ready`, `not_ready` or `shutting_down`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<Checks>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Checks {
+    database: Check,
+    jwks: Check,
+    schema: Check,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+struct Check {
+    /// `ok` or `fail`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    age_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn ok() -> Self {
+        Check { status: "ok", ..Check::default() }
+    }
+
+    fn fail(error: impl Into<String>) -> Self {
+        Check { status: "fail", error: Some(error.into()), ..Check::default() }
+    }
+
+    fn passed(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Readiness: the database answers, signing keys are loaded and fresh, and the
+/// required tables exist. Answers 503 with the per-component breakdown otherwise,
+/// and unconditionally once shutdown has started so traffic drains away.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = Readiness),
+        (status = 503, description = "A check failed, or shutdown has started", body = Readiness),
+    )
+)]
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    if state.shutdown.is_triggered() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(Readiness { status: "shutting_down", checks: None }));
+    }
+
+    let (database, schema) = tokio::join!(check_database(&state), check_schema(&state));
+    let checks = Checks { database, jwks: check_jwks(&state), schema };
+
+    let ready = checks.database.passed() && checks.jwks.passed() && checks.schema.passed();
+    let (status, label) = if ready { (StatusCode::OK, "ready") } else { (StatusCode::SERVICE_UNAVAILABLE, "not_ready") };
+    (status, Json(Readiness { status: label, checks: Some(checks) }))
+}
+
+async fn check_database(state: &AppState) -> Check {
+    let started = Instant::now();
+    let mut check = match tokio::time::timeout(DB_CHECK_TIMEOUT, db::test_db_connection(&state.pool)).await {
+        Ok(Ok(())) => Check::ok(),
+        Ok(Err(e)) => Check::fail(e.to_string()),
+        Err(_) => Check::fail(format!("no response within {}s", DB_CHECK_TIMEOUT.as_secs())),
+    };
+    check.latency_ms = Some(started.elapsed().as_millis() as u64);
+    check
+}
+
+fn check_jwks(state: &AppState) -> Check {
+    let Some(age) = state.keys.age() else {
+        return Check::fail("signing keys not loaded");
+    };
+    let mut check = match state.config.auth.jwks_max_age() {
+        Some(max_age) if age > max_age => {
+            Check::fail(format!("signing keys not refreshed for over {}s", max_age.as_secs()))
+        }
+        _ => Check::ok(),
+    };
+    check.age_secs = Some(age.as_secs());
+    check
+}
+
+async fn check_schema(state: &AppState) -> Check {
+    match tokio::time::timeout(DB_CHECK_TIMEOUT, db::missing_schema_objects(&state.pool)).await {
+        Ok(Ok(missing)) if missing.is_empty() => Check::ok(),
+        Ok(Ok(missing)) => Check { missing, ..Check::fail("schema is missing or outdated; apply src/db/schema.sql") },
+        Ok(Err(e)) => Check::fail(e.to_string()),
+        Err(_) => Check::fail(format!("no response within {}s", DB_CHECK_TIMEOUT.as_secs())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::{to_bytes, Body}, http::Request};
+    use serde_json::Value;
+    use sqlx::postgres::PgPoolOptions;
+    use tower::ServiceExt;
+
+    use crate::config::AppConfig;
+
+    #[tokio::test]
+    async fn test_readyz_reports_each_failing_component() {
+        // Nothing listens on port 1; a short acquire timeout makes the database checks fail fast.
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/unused")
+            .unwrap();
+        let app = health_routes().with_state(AppState::new(pool, AppConfig::default()));
+
+        let response = app.clone().oneshot(Request::get("/healthz").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(Request::get("/readyz").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["database"]["status"], "fail");
+        assert_eq!(body["checks"]["jwks"]["error"], "signing keys not loaded");
+    }
+}
@@ -0,0 +1,192 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequestParts, Json, State},
+    http::request::Parts,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::webhooks::{SignatureScheme, WebhookError, WebhookVerifier};
+use crate::auth::user_context::UserRole;
+use crate::db::models::UserRoleRecord;
+use crate::db::user_role_repository;
+use crate::db::DbError;
+use crate::state::AppState;
+
+/// The custom access token hook's verifier from `AppState`. The hook routes are only
+/// mounted when it is configured, so the rejection is a safety net.
+#[axum::async_trait]
+impl FromRequestParts<AppState> for Arc<WebhookVerifier> {
+    type Rejection = HookError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state.hook_verifier.clone().ok_or(HookError::NotConfigured)
+    }
+}
+
+/// Errors returned to Supabase Auth. Any non-2xx response makes GoTrue refuse to issue the token.
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    /// Bad signature or replayed message.
+    #[error(transparent)]
+    Rejected(#[from] WebhookError),
+
+    #[error("Invalid hook payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("Custom access token hook is not configured")]
+    NotConfigured,
+
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl IntoResponse for HookError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            // Same error shape, with 401 or 409.
+            HookError::Rejected(e) => return e.into_response(),
+            e @ HookError::InvalidPayload(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            e @ HookError::NotConfigured => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+            HookError::Db(e) => {
+                tracing::error!(error = %e, "Custom access token hook failed");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Could not load user roles".to_string())
+            }
+        };
+        // Supabase Auth expects hook errors in this shape.
+        let body = json!({ "error": { "http_code": status.as_u16(), "message": message } });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Payload Supabase Auth sends to the custom access token hook.
+#[derive(Debug, Deserialize)]
+pub struct CustomAccessTokenPayload {
+    pub user_id: Uuid,
+    pub claims: Map<String, Value>,
+}
+
+/// Supabase Auth hooks. Mounted outside `/api`: requests are authenticated by their
+/// signature, not by a user JWT.
+pub fn hook_routes() -> Router<AppState> {
+    Router::new().route("/custom-access-token", post(custom_access_token_handler))
+}
+
+/// Custom access token hook: adds the role, permissions and tenant from
+/// `public.user_roles` to the claims before GoTrue signs the JWT.
+#[utoipa::path(
+    post,
+    path = "/hooks/custom-access-token",
+    tag = "hooks",
+    request_body(content = Object, description = "`{ user_id, claims }` as sent by Supabase Auth"),
+    responses(
+        (status = 200, description = "`{ claims }` with the role, permissions and tenant added", body = Object),
+        (status = 400, description = "Malformed payload"),
+        (status = 401, description = "Missing or invalid signature"),
+        (status = 409, description = "Replayed delivery"),
+    ),
+    security(("standard_webhooks" = []))
+)]
+async fn custom_access_token_handler(
+    State(pool): State<PgPool>,
+    verifier: Arc<WebhookVerifier>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, HookError> {
+    let delivery = verifier.verify(SignatureScheme::StandardWebhooks, &headers, &body)?;
+
+    let payload: CustomAccessTokenPayload =
+        serde_json::from_slice(&body).map_err(|e| HookError::InvalidPayload(e.to_string()))?;
+    let record = user_role_repository::get_user_role(&pool, payload.user_id).await?;
+    delivery.complete();
+
+    let claims = augment_claims(payload.claims, record.as_ref());
+    Ok(Json(json!({ "claims": claims })))
+}
+
+/// Adds `user_role`, `permissions`, `tenant_id` and `app_metadata.role`.
+/// The top-level `role` claim is left alone: PostgREST uses it as the Postgres role.
+fn augment_claims(mut claims: Map<String, Value>, record: Option<&UserRoleRecord>) -> Map<String, Value> {
+    let role = record
+        .map(|r| UserRole::from_claim(Some(r.role.as_str())))
+        .unwrap_or_default()
+        .to_string();
+    let permissions = record.map(|r| r.permissions.clone()).unwrap_or_default();
+
+    claims.insert("user_role".to_string(), Value::String(role.clone()));
+    claims.insert("permissions".to_string(), json!(permissions));
+    match record.and_then(|r| r.tenant_id) {
+        Some(tenant_id) => claims.insert("tenant_id".to_string(), Value::String(tenant_id.to_string())),
+        None => claims.remove("tenant_id"),
+    };
+
+    let app_metadata = claims
+        .entry("app_metadata")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !app_metadata.is_object() {
+        *app_metadata = Value::Object(Map::new());
+    }
+    app_metadata["role"] = Value::String(role);
+
+    claims
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    use crate::test_support::{json_body, lazy_pool, test_config, test_pool};
+    use crate::webhooks::signature::standard_webhook_headers;
+
+    fn app(pool: PgPool) -> Router {
+        let mut config = test_config();
+        config.auth.hook_secret = Some(format!("v1,whsec_{}", STANDARD.encode(b"secret")));
+        Router::new().nest("/hooks", hook_routes()).with_state(AppState::new(pool, config))
+    }
+
+    fn hook_request(secret: &[u8], id: &str) -> Request<Body> {
+        let body = json!({ "user_id": Uuid::new_v4(), "claims": { "sub": "abc" } }).to_string();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut request = Request::post("/hooks/custom-access-token").body(Body::from(body.clone())).unwrap();
+        request.headers_mut().extend(standard_webhook_headers(secret, id, now, body.as_bytes()));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_without_a_secret() {
+        let app = Router::new().nest("/hooks", hook_routes()).with_state(AppState::new(lazy_pool(), test_config()));
+        let response = app.oneshot(hook_request(b"secret", "msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_signature() {
+        let response = app(lazy_pool()).oneshot(hook_request(b"other", "msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(json_body(response).await["error"]["http_code"], 401);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_replayed_message() {
+        let Some(pool) = test_pool().await else { return };
+        let app = app(pool);
+
+        let response = app.clone().oneshot(hook_request(b"secret", "msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["claims"]["user_role"], "user");
+
+        let response = app.oneshot(hook_request(b"secret", "msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
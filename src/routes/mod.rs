@@ -1,14 +1,36 @@
-use sqlx::PgPool;
+use axum::{middleware, Router};
+
+use crate::auth::middleware::jwt_auth_middleware;
+use crate::rate_limit::rate_limit_middleware;
+use crate::state::AppState;
+
+pub mod admin_routes;
 pub mod profile_routes;
 pub mod echo_routes;
+pub mod extract;
+pub mod health_routes;
+pub mod hook_routes;
+pub mod path;
+pub mod webhook_routes;
 
 // AI: Add other route modules here as the application grows
 
-// Function to combine all application routes
-pub fn app_routes(pool: PgPool) -> axum::Router {
-    axum::Router::new()
-        .nest("/api/profiles", profile_routes::profile_routes(pool.clone()))
-        .nest("/api", echo_routes::echo_routes(pool.clone()))
+// Function to combine all application routes. The result is nested under `/api` in main
+// and protected by the JWT auth middleware.
+// Admin user management is only mounted when the auth admin API is configured.
+pub fn app_routes(state: &AppState) -> Router<AppState> {
+    let mut router = Router::new()
+        .nest("/profiles", profile_routes::profile_routes(state))
+        .merge(echo_routes::echo_routes());
     // AI: Nest other route modules here, e.g.:
-    // .nest("/api/items", items_routes::items_routes(pool.clone()))
+    // .nest("/items", items_routes::items_routes(state))
+
+    if state.gotrue.is_some() {
+        router = router.nest("/admin", admin_routes::admin_routes());
+    }
+
+    router
+        // The rate limit runs inside the auth middleware so it can key on the AuthUser.
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(state.clone(), jwt_auth_middleware))
 }
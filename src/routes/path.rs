@@ -0,0 +1,25 @@
+use axum::extract::{rejection::PathRejection, FromRequestParts, Path};
+use axum::http::request::Parts;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// The `:user_id` path parameter. A malformed id is rejected with a 400 problem
+/// (`invalid_user_id`) rather than axum's plain-text rejection.
+#[derive(Debug, Clone, Copy)]
+pub struct UserIdPath(pub Uuid);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for UserIdPath {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<Uuid>::from_request_parts(parts, state).await {
+            Ok(Path(user_id)) => Ok(UserIdPath(user_id)),
+            Err(PathRejection::FailedToDeserializePathParams(_)) => {
+                Err(AppError::bad_request("invalid_user_id", "User ID must be a UUID"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -1,102 +1,779 @@
 use axum::{
-    extract::{State, Json, Path},
-    response::{Response, IntoResponse},
-    routing::{get, post, put, delete},
+    extract::{multipart::Field, rejection::QueryRejection, DefaultBodyLimit, Multipart, State},
+    middleware,
+    response::IntoResponse,
+    routing::{get, post, put, patch, delete},
     Router,
     http::StatusCode,
 };
-use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::auth::user_context::AuthUser;
-use crate::db::profile_repository;
-use crate::db::models::{CreateProfilePayload, UpdateProfilePayload, UserProfile};
-use crate::db::DbError;
-
-// AI: Error handling for API routes. This could be part of a larger AppError in Phase 4.1.
-// For now, converting DbError into an appropriate HTTP response.
-impl IntoResponse for DbError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            DbError::DatabaseUrlNotSet => (StatusCode::INTERNAL_SERVER_ERROR, "Server configuration error".to_string()),
-            DbError::PoolCreationFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Could not connect to database".to_string()),
-            DbError::ConnectionError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database connection issue".to_string()),
-            DbError::ProfileNotFound => (StatusCode::NOT_FOUND, "Profile not found".to_string()),
-            DbError::QueryError(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Database query failed: {}", e)),
-            DbError::ProfileCreationError(e) => (StatusCode::BAD_REQUEST, format!("Could not create profile: {}", e)), // Or INTERNAL_SERVER_ERROR depending on cause
-            DbError::ProfileUpdateError(e) => (StatusCode::BAD_REQUEST, format!("Could not update profile: {}", e)), // Or INTERNAL_SERVER_ERROR
-            DbError::ProfileDeleteError(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not delete profile: {}", e)),
-        };
-        (status, Json(serde_json::json!({ "error": error_message }))).into_response()
-    }
-}
+use crate::auth::user_context::{AdminUser, AuthUser, UserRole};
+use crate::db::audit_repository;
+use crate::avatar::{self, ACCEPTED_TYPES};
+use crate::db::profile_repository::{self, ProfileSort};
+use crate::db::tx::{tx_middleware, Tx};
+use crate::db::models::{
+    merge_patch, profile_changes, CreateProfilePayload, Patch, ProfileAuditAction, ProfileFilter, ProfilePatch, PublicProfile,
+    UpdateProfilePayload, UserProfile,
+};
+use crate::error::{AppError, Problem};
+use crate::listing::{ListParams, ListQuery, Page};
+use crate::routes::extract::{Json, Query};
+use crate::routes::path::UserIdPath;
+use crate::state::AppState;
+use crate::storage::Storage;
+use crate::validation::{self, ProfileValidator};
 
-// Router is Router<PgPool>, state is provided by with_state
-pub fn profile_routes(pool: PgPool) -> Router {
+// Handlers extract `State<PgPool>` from the shared `AppState`
+pub fn profile_routes(state: &AppState) -> Router<AppState> {
     Router::new()
+        .route("/", get(list_profiles_handler))
         .route("/me", get(get_my_profile_handler))
         .route("/me", post(create_my_profile_handler))
         .route("/me", put(update_my_profile_handler))
+        .route("/me", patch(patch_my_profile_handler))
         .route("/me", delete(delete_my_profile_handler))
-        // AI: Admin routes for managing any user's profile could be added here, protected by role-based auth
+        // Uploads get their own body limit; room is left for the multipart framing.
+        .route(
+            "/me/avatar",
+            post(upload_avatar_handler).layer(DefaultBodyLimit::max(state.config.storage.avatar_max_bytes + 64 * 1024)),
+        )
+        .route("/username-available", get(username_available_handler))
+        .route("/search", get(search_profiles_handler))
+        // Any user's profile; every handler requires the Admin role
         .route("/:user_id", get(get_user_profile_handler))
-        .with_state(pool)
+        .route("/:user_id", put(update_user_profile_handler))
+        .route("/:user_id", patch(patch_user_profile_handler))
+        .route("/:user_id", delete(delete_user_profile_handler))
+        // Provides the `Tx` extractor; commits when the handler succeeds
+        .route_layer(middleware::from_fn_with_state(state.clone(), tx_middleware))
+}
+
+/// The token's `sub` as a UUID. Supabase always issues UUID subjects.
+fn subject_id(auth_user: &AuthUser) -> Result<Uuid, AppError> {
+    auth_user.id.parse().map_err(|_| AppError::unauthorized("invalid_subject", "Token subject is not a valid user ID"))
 }
 
 /// Handler to create the authenticated user's profile.
+#[utoipa::path(
+    post,
+    path = "/api/profiles/me",
+    tag = "profiles",
+    request_body = CreateProfilePayload,
+    responses(
+        (status = 201, description = "Profile created", body = UserProfile),
+        (status = 409, description = "The user already has a profile (`profile_exists`), or the username is taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "A field is invalid (`validation_failed`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn create_my_profile_handler(
     auth_user: AuthUser, // Extracted from JWT
-    State(pool): State<PgPool>,
+    mut tx: Tx, // Runs under the user's RLS policies
+    State(validator): State<Arc<ProfileValidator>>,
     Json(payload): Json<CreateProfilePayload>,
-) -> Result<impl IntoResponse, DbError> {
-    let user_id = auth_user.id.parse().map_err(|_| DbError::QueryError(sqlx::Error::Decode("Invalid user ID format in token".into())))?;
-    let profile = profile_repository::create_profile(&pool, user_id, auth_user.email, payload).await?;
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    validator.check_create(&payload)?;
+    let profile = profile_repository::create_profile(tx.conn().await?, user_id, auth_user.email, payload).await?;
     Ok((StatusCode::CREATED, Json(profile)))
 }
 
 /// Handler to get the authenticated user's profile.
+#[utoipa::path(
+    get,
+    path = "/api/profiles/me",
+    tag = "profiles",
+    responses(
+        (status = 200, description = "The caller's profile", body = UserProfile),
+        (status = 404, description = "No profile yet (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn get_my_profile_handler(
     auth_user: AuthUser, // Extracted from JWT
-    State(pool): State<PgPool>,
-) -> Result<Json<UserProfile>, DbError> {
-    let user_id = auth_user.id.parse().map_err(|_| DbError::QueryError(sqlx::Error::Decode("Invalid user ID format in token".into())))?;
-    let profile = profile_repository::get_profile_by_id(&pool, user_id).await?;
+    mut tx: Tx, // Runs under the user's RLS policies
+) -> Result<Json<UserProfile>, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    let profile = profile_repository::get_profile_by_id(tx.conn().await?, user_id).await?;
     Ok(Json(profile))
 }
 
-/// Handler to update the authenticated user's profile.
+/// Handler to replace the authenticated user's profile. Every field must be sent;
+/// use PATCH to change only some of them.
+#[utoipa::path(
+    put,
+    path = "/api/profiles/me",
+    tag = "profiles",
+    request_body = UpdateProfilePayload,
+    responses(
+        (status = 200, description = "Updated profile", body = UserProfile),
+        (status = 422, description = "A field is missing, has the wrong type, or is invalid (`validation_failed`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile yet (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Username already taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn update_my_profile_handler(
     auth_user: AuthUser, // Extracted from JWT
-    State(pool): State<PgPool>,
+    mut tx: Tx, // Runs under the user's RLS policies
+    State(validator): State<Arc<ProfileValidator>>,
     Json(payload): Json<UpdateProfilePayload>,
-) -> Result<Json<UserProfile>, DbError> {
-    let user_id = auth_user.id.parse().map_err(|_| DbError::QueryError(sqlx::Error::Decode("Invalid user ID format in token".into())))?;
-    let profile = profile_repository::update_profile(&pool, user_id, payload).await?;
+) -> Result<Json<UserProfile>, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    validator.check_update(&payload)?;
+    let profile = profile_repository::update_profile(tx.conn().await?, user_id, payload).await?;
     Ok(Json(profile))
 }
 
+/// Handler to partially update the authenticated user's profile with a JSON Merge
+/// Patch (RFC 7396): absent fields are left alone and `null` clears a field.
+#[utoipa::path(
+    patch,
+    path = "/api/profiles/me",
+    tag = "profiles",
+    request_body(content = ProfilePatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Updated profile", body = UserProfile),
+        (status = 404, description = "No profile yet (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Username already taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "A field is unknown, has the wrong type, or is invalid (`validation_failed`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+async fn patch_my_profile_handler(
+    auth_user: AuthUser, // Extracted from JWT
+    mut tx: Tx, // Runs under the user's RLS policies
+    State(validator): State<Arc<ProfileValidator>>,
+    // `Json` accepts `application/merge-patch+json` as well as `application/json`.
+    Json(patch): Json<ProfilePatch>,
+) -> Result<Json<UserProfile>, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    let profile = apply_patch(tx.conn().await?, &validator, user_id, patch).await?;
+    Ok(Json(profile))
+}
+
+/// Validates and applies a merge patch on `conn`, which must be in a transaction.
+async fn apply_patch(
+    conn: &mut PgConnection,
+    validator: &ProfileValidator,
+    user_id: Uuid,
+    mut patch: ProfilePatch,
+) -> Result<UserProfile, AppError> {
+    // Merge into the stored metadata (locked until commit) so the schema checks the final object.
+    if let Patch::Value(changes) = &patch.metadata {
+        let mut metadata = profile_repository::lock_metadata(&mut *conn, user_id).await?;
+        merge_patch(&mut metadata, changes);
+        patch.metadata = Patch::Value(metadata);
+    }
+    validator.check_patch(&patch)?;
+    Ok(profile_repository::patch_profile(conn, user_id, &patch).await?)
+}
+
 /// Handler to delete the authenticated user's profile.
+#[utoipa::path(
+    delete,
+    path = "/api/profiles/me",
+    tag = "profiles",
+    responses(
+        (status = 204, description = "Profile deleted"),
+        (status = 404, description = "No profile (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn delete_my_profile_handler(
     auth_user: AuthUser, // Extracted from JWT
-    State(pool): State<PgPool>,
-) -> Result<StatusCode, DbError> {
-    let user_id = auth_user.id.parse().map_err(|_| DbError::QueryError(sqlx::Error::Decode("Invalid user ID format in token".into())))?;
-    profile_repository::delete_profile(&pool, user_id).await?;
+    mut tx: Tx, // Runs under the user's RLS policies
+) -> Result<StatusCode, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    profile_repository::delete_profile(tx.conn().await?, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// Admin handler to get any user's profile by ID (requires role check)
+/// Multipart form accepted by `POST /profiles/me/avatar`. Only used for the OpenAPI document.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct AvatarForm {
+    /// JPEG, PNG or WebP image.
+    #[schema(value_type = String, format = Binary)]
+    avatar: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarResponse {
+    pub profile: UserProfile,
+    /// Thumbnail URLs keyed by edge length in pixels (`"512"`, `"256"`, `"128"`).
+    pub thumbnails: BTreeMap<String, String>,
+}
+
+/// Handler to upload the authenticated user's avatar. The image in the `avatar`
+/// field is re-encoded into square JPEG thumbnails; `avatar_url` is set to the largest.
+#[utoipa::path(
+    post,
+    path = "/api/profiles/me/avatar",
+    tag = "profiles",
+    request_body(content = AvatarForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Avatar stored", body = AvatarResponse),
+        (status = 400, description = "No `avatar` field, or a malformed form (`invalid_upload`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile yet (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "Image larger than `AVATAR_MAX_BYTES` (`upload_too_large`)", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "Not a JPEG, PNG or WebP image (`unsupported_image`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The image could not be decoded (`invalid_image`)", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "Storage service unavailable (`storage_unavailable`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+async fn upload_avatar_handler(
+    auth_user: AuthUser, // Extracted from JWT
+    mut tx: Tx, // Runs under the user's RLS policies
+    State(storage): State<Arc<dyn Storage>>,
+    State(config): State<Arc<crate::config::AppConfig>>,
+    mut multipart: Multipart,
+) -> Result<Json<AvatarResponse>, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    let max_bytes = config.storage.avatar_max_bytes;
+
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await.map_err(|e| upload_error(e, max_bytes))? {
+        if field.name() == Some("avatar") {
+            upload = Some(read_image_field(field, max_bytes).await?);
+            break;
+        }
+    }
+    let upload = upload.ok_or_else(|| AppError::bad_request("invalid_upload", "The form has no `avatar` field"))?;
+
+    let rendered = tokio::task::spawn_blocking(move || avatar::thumbnails(&upload))
+        .await
+        .map_err(|e| AppError::internal("thumbnail_failed", e))??;
+
+    // Keys are stable per user, so the version parameter makes caches fetch the new image.
+    let version = chrono::Utc::now().timestamp_millis();
+    let mut thumbnails = BTreeMap::new();
+    for (size, jpeg) in rendered {
+        let url = storage.put(&format!("avatars/{}/{}.jpg", user_id, size), jpeg.into(), "image/jpeg").await?;
+        thumbnails.insert(size.to_string(), format!("{}?v={}", url, version));
+    }
+
+    // The database is only touched now, so no connection is held while the image is processed.
+    let patch = ProfilePatch {
+        avatar_url: Patch::Value(thumbnails[&avatar::THUMBNAIL_SIZES[0].to_string()].clone()),
+        ..Default::default()
+    };
+    let profile = profile_repository::patch_profile(tx.conn().await?, user_id, &patch).await?;
+    Ok(Json(AvatarResponse { profile, thumbnails }))
+}
+
+/// Reads the uploaded file after checking its declared type and size.
+async fn read_image_field(field: Field<'_>, max_bytes: usize) -> Result<Vec<u8>, AppError> {
+    let content_type = field.content_type().unwrap_or_default();
+    if !ACCEPTED_TYPES.contains(&content_type) {
+        return Err(AppError::UnsupportedMediaType {
+            code: "unsupported_image",
+            detail: format!("Expected one of {}, got '{}'", ACCEPTED_TYPES.join(", "), content_type),
+        });
+    }
+    let bytes = field.bytes().await.map_err(|e| upload_error(e, max_bytes))?;
+    if bytes.len() > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+    Ok(bytes.to_vec())
+}
+
+fn upload_error(err: axum::extract::multipart::MultipartError, max_bytes: usize) -> AppError {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        // Hit the route's body limit, which leaves a little room for the form framing.
+        too_large(max_bytes)
+    } else {
+        AppError::bad_request("invalid_upload", err.body_text())
+    }
+}
+
+fn too_large(max_bytes: usize) -> AppError {
+    AppError::PayloadTooLarge { code: "upload_too_large", detail: format!("The image must be at most {} bytes", max_bytes) }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsernameQuery {
+    /// Username to check.
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsernameAvailability {
+    pub name: String,
+    pub available: bool,
+    /// Why the name cannot be used, when `available` is false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Handler to check whether a username is valid and free, e.g. while the user types it.
+/// The caller's own username counts as available. Saving can still return 409 if
+/// someone else takes the name first.
+#[utoipa::path(
+    get,
+    path = "/api/profiles/username-available",
+    tag = "profiles",
+    params(UsernameQuery),
+    responses((status = 200, description = "Whether the username can be used", body = UsernameAvailability)),
+    security(("bearer_auth" = []))
+)]
+async fn username_available_handler(
+    auth_user: AuthUser, // Extracted from JWT
+    State(pool): State<PgPool>, // Needs to see every profile, which RLS would hide
+    Query(query): Query<UsernameQuery>,
+) -> Result<Json<UsernameAvailability>, AppError> {
+    let user_id = subject_id(&auth_user)?;
+    let reason = match validation::check_username(&query.name) {
+        Err(message) => Some(message),
+        Ok(()) if profile_repository::username_taken(&pool, &query.name, user_id).await? => {
+            Some("is already taken".to_string())
+        }
+        Ok(()) => None,
+    };
+    Ok(Json(UsernameAvailability { name: query.name, available: reason.is_none(), reason }))
+}
+
+/// Longest accepted search text, in characters.
+const SEARCH_MAX_CHARS: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// Words to find in usernames and full names; each matches as a prefix.
+    pub q: String,
+    /// Number of results, 1 to 50 (default 10).
+    pub limit: Option<u32>,
+}
+
+/// A search result: the full profile for admins, the public fields for everyone else.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum SearchHit {
+    Full(UserProfile),
+    Public(PublicProfile),
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResults {
+    /// Best match first.
+    pub items: Vec<SearchHit>,
+}
+
+/// Handler to find profiles by username or full name, for typeahead and people search.
+#[utoipa::path(
+    get,
+    path = "/api/profiles/search",
+    tag = "profiles",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching profiles, best first", body = SearchResults),
+        (status = 400, description = "`q` is empty or too long, or `limit` is out of range (`invalid_search`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+async fn search_profiles_handler(
+    auth_user: AuthUser, // Extracted from JWT
+    State(pool): State<PgPool>, // Searches every profile; non-admins only get public fields
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResults>, AppError> {
+    let text = query.q.trim();
+    if text.is_empty() || text.chars().count() > SEARCH_MAX_CHARS {
+        return Err(AppError::bad_request("invalid_search", format!("q must be 1 to {} characters", SEARCH_MAX_CHARS)));
+    }
+    let limit = query.limit.unwrap_or(10);
+    if !(1..=50).contains(&limit) {
+        return Err(AppError::bad_request("invalid_search", "limit must be between 1 and 50"));
+    }
+
+    let profiles = profile_repository::search_profiles(&pool, text, limit.into()).await?;
+    let items = profiles
+        .into_iter()
+        .map(|profile| match auth_user.role {
+            UserRole::Admin => SearchHit::Full(profile),
+            _ => SearchHit::Public(profile.into()),
+        })
+        .collect();
+    Ok(Json(SearchResults { items }))
+}
+
+/// Admin handler to list profiles, with filters, sorting and offset or cursor pagination.
+#[utoipa::path(
+    get,
+    path = "/api/profiles",
+    tag = "profiles",
+    params(ProfileFilter, ListParams),
+    responses(
+        (status = 200, description = "One page of profiles; `sort` accepts `created_at` (default `-created_at`), `updated_at`, `email` and `username`", body = Page<UserProfile>),
+        (status = 400, description = "Invalid filter, sort, limit or cursor (`invalid_list_query`)", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not an admin (`forbidden`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn list_profiles_handler(
+    _admin: AdminUser,
+    State(pool): State<PgPool>, // Admins see every profile, bypassing RLS
+    // Keeps the `invalid_list_query` code shared with `ListQuery`.
+    filter: Result<axum::extract::Query<ProfileFilter>, QueryRejection>,
+    list: ListQuery<ProfileSort>,
+) -> Result<Json<Page<UserProfile>>, AppError> {
+    let axum::extract::Query(filter) = filter.map_err(|e| AppError::bad_request("invalid_list_query", e.body_text()))?;
+    let page = profile_repository::list_profiles(&pool, &filter, &list).await?;
+    Ok(Json(page))
+}
+
+/// Admin handler to get any user's profile by ID.
+#[utoipa::path(
+    get,
+    path = "/api/profiles/{user_id}",
+    tag = "profiles",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    responses(
+        (status = 200, description = "The user's profile", body = UserProfile),
+        (status = 400, description = "`user_id` is not a UUID (`invalid_user_id`)", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not an admin (`forbidden`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
 async fn get_user_profile_handler(
-    Path(user_id_str): Path<String>,
-    auth_user: AuthUser, // For role check
+    _admin: AdminUser,
     State(pool): State<PgPool>,
-) -> Result<Json<UserProfile>, DbError> {
-    // Implement role check here
-    if auth_user.role != crate::auth::user_context::UserRole::Admin {
-        return Err(DbError::QueryError(sqlx::Error::Decode("Unauthorized: Admin role required".into())));
-    }
-    
-    let user_id = Uuid::parse_str(&user_id_str).map_err(|_| DbError::QueryError(sqlx::Error::Decode("Invalid UUID format".into())))?;
+    UserIdPath(user_id): UserIdPath,
+) -> Result<Json<UserProfile>, AppError> {
+    // Admins read through the pool's own role: the RLS policies only expose a user's own row.
     let profile = profile_repository::get_profile_by_id(&pool, user_id).await?;
     Ok(Json(profile))
-} 
\ No newline at end of file
+}
+
+/// Admin handler to replace any user's profile, with the same rules as `PUT /me`.
+#[utoipa::path(
+    put,
+    path = "/api/profiles/{user_id}",
+    tag = "profiles",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    request_body = UpdateProfilePayload,
+    responses(
+        (status = 200, description = "Updated profile", body = UserProfile),
+        (status = 400, description = "`user_id` is not a UUID (`invalid_user_id`)", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not an admin (`forbidden`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Username already taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "A field is missing, has the wrong type, or is invalid (`validation_failed`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn update_user_profile_handler(
+    AdminUser(admin): AdminUser,
+    State(pool): State<PgPool>,
+    State(validator): State<Arc<ProfileValidator>>,
+    UserIdPath(user_id): UserIdPath,
+    Json(payload): Json<UpdateProfilePayload>,
+) -> Result<Json<UserProfile>, AppError> {
+    let admin_id = subject_id(&admin)?;
+    validator.check_update(&payload)?;
+    let mut tx = pool.begin().await?;
+    let before = profile_repository::lock_profile(&mut *tx, user_id).await?;
+    let profile = profile_repository::update_profile(&mut *tx, user_id, payload).await?;
+    audit(&mut tx, admin_id, ProfileAuditAction::Update, &before, Some(&profile)).await?;
+    tx.commit().await?;
+    Ok(Json(profile))
+}
+
+/// Admin handler to merge-patch any user's profile, with the same rules as `PATCH /me`.
+#[utoipa::path(
+    patch,
+    path = "/api/profiles/{user_id}",
+    tag = "profiles",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    request_body(content = ProfilePatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Updated profile", body = UserProfile),
+        (status = 400, description = "`user_id` is not a UUID (`invalid_user_id`)", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not an admin (`forbidden`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Username already taken (`already_taken`, with `field`)", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "A field is unknown, has the wrong type, or is invalid (`validation_failed`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn patch_user_profile_handler(
+    AdminUser(admin): AdminUser,
+    State(pool): State<PgPool>,
+    State(validator): State<Arc<ProfileValidator>>,
+    UserIdPath(user_id): UserIdPath,
+    Json(patch): Json<ProfilePatch>,
+) -> Result<Json<UserProfile>, AppError> {
+    let admin_id = subject_id(&admin)?;
+    let mut tx = pool.begin().await?;
+    let before = profile_repository::lock_profile(&mut *tx, user_id).await?;
+    let profile = apply_patch(&mut tx, &validator, user_id, patch).await?;
+    audit(&mut tx, admin_id, ProfileAuditAction::Patch, &before, Some(&profile)).await?;
+    tx.commit().await?;
+    Ok(Json(profile))
+}
+
+/// Admin handler to delete any user's profile. The auth user is kept; use
+/// `DELETE /api/admin/users/{user_id}` to remove both.
+#[utoipa::path(
+    delete,
+    path = "/api/profiles/{user_id}",
+    tag = "profiles",
+    params(("user_id" = Uuid, Path, description = "Auth user ID")),
+    responses(
+        (status = 204, description = "Profile deleted"),
+        (status = 400, description = "`user_id` is not a UUID (`invalid_user_id`)", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not an admin (`forbidden`)", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No profile (`profile_not_found`)", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = ["admin"]))
+)]
+async fn delete_user_profile_handler(
+    AdminUser(admin): AdminUser,
+    State(pool): State<PgPool>,
+    UserIdPath(user_id): UserIdPath,
+) -> Result<StatusCode, AppError> {
+    let admin_id = subject_id(&admin)?;
+    let mut tx = pool.begin().await?;
+    let before = profile_repository::lock_profile(&mut *tx, user_id).await?;
+    profile_repository::delete_profile(&mut *tx, user_id).await?;
+    audit(&mut tx, admin_id, ProfileAuditAction::Delete, &before, None).await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Records an admin's change in the audit log, unless nothing actually changed.
+async fn audit(
+    conn: &mut PgConnection,
+    admin_id: Uuid,
+    action: ProfileAuditAction,
+    before: &UserProfile,
+    after: Option<&UserProfile>,
+) -> Result<(), AppError> {
+    let changes = profile_changes(before, after);
+    if changes.as_object().is_some_and(|changes| changes.is_empty()) {
+        return Ok(());
+    }
+    tracing::info!(%admin_id, user_id = %before.id, action = action.as_str(), "Admin changed profile");
+    audit_repository::record_profile_change(conn, admin_id, before.id, action, &changes).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, StatusCode};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_support::{authed, json_body, lazy_pool, test_config, test_pool, test_state, test_user, token};
+
+    fn app(state: AppState) -> Router {
+        Router::new().nest("/api", crate::routes::app_routes(&state)).with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_bad_text_and_limit() {
+        let app = app(test_state(lazy_pool(), test_config()));
+        let user = token(&Uuid::new_v4().to_string(), None);
+        let long = "a".repeat(SEARCH_MAX_CHARS + 1);
+        for query in ["q=%20", &format!("q={}", long), "q=ada&limit=0", "q=ada&limit=51"] {
+            let response = app.clone().oneshot(authed("GET", &format!("/api/profiles/search?{}", query), &user, None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+            assert_eq!(json_body(response).await["code"], "invalid_search");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_shows_full_profiles_to_admins_only() {
+        let Some(pool) = test_pool().await else { return };
+        let id = test_user(&pool).await;
+        let username = format!("q{}", &Uuid::new_v4().simple().to_string()[..10]);
+        let payload = CreateProfilePayload { username: Some(username.clone()), ..Default::default() };
+        let email = format!("{}@example.com", id);
+        profile_repository::create_profile(&pool, id, Some(email.clone()), payload).await.unwrap();
+        let app = app(test_state(pool, test_config()));
+        let uri = format!("/api/profiles/search?q={}", username);
+
+        let admin = token(&Uuid::new_v4().to_string(), Some("admin"));
+        let body = json_body(app.clone().oneshot(authed("GET", &uri, &admin, None)).await.unwrap()).await;
+        assert_eq!(body["items"][0]["id"], id.to_string());
+        assert_eq!(body["items"][0]["email"], email);
+
+        let user = token(&Uuid::new_v4().to_string(), None);
+        let body = json_body(app.oneshot(authed("GET", &uri, &user, None)).await.unwrap()).await;
+        let hit = body["items"][0].as_object().unwrap();
+        assert_eq!(hit["id"], id.to_string());
+        assert_eq!(hit["username"], username);
+        assert!(!hit.contains_key("email") && !hit.contains_key("metadata"), "{:?}", hit);
+    }
+
+    #[tokio::test]
+    async fn test_admin_profile_routes_check_role_then_id() {
+        let app = app(test_state(lazy_pool(), test_config()));
+        let admin = token(&Uuid::new_v4().to_string(), Some("admin"));
+        let user = token(&Uuid::new_v4().to_string(), Some("premium"));
+        let id = Uuid::new_v4();
+        for (method, body) in [("GET", None), ("PUT", Some(json!({}))), ("PATCH", Some(json!({}))), ("DELETE", None)] {
+            let response = app.clone().oneshot(authed(method, "/api/profiles/not-a-uuid", &admin, body.clone())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", method);
+            assert_eq!(json_body(response).await["code"], "invalid_user_id");
+
+            let response = app.clone().oneshot(authed(method, &format!("/api/profiles/{}", id), &user, body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", method);
+            assert_eq!(json_body(response).await["code"], "forbidden");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_requests_are_problems() {
+        let app = app(test_state(lazy_pool(), test_config()));
+        let admin = token(&Uuid::new_v4().to_string(), Some("admin"));
+        let uri = format!("/api/profiles/{}", Uuid::new_v4());
+        let raw = |content_type: &str, body: &str| {
+            let mut request = authed("PUT", &uri, &admin, None);
+            request.headers_mut().insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            *request.body_mut() = Body::from(body.to_string());
+            request
+        };
+        let requests = [
+            (authed("PUT", &uri, &admin, Some(json!({}))), StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+            (raw("application/json", "{"), StatusCode::BAD_REQUEST, "invalid_body"),
+            (raw("text/plain", "{}"), StatusCode::UNSUPPORTED_MEDIA_TYPE, "invalid_body"),
+            (authed("GET", "/api/profiles/username-available", &admin, None), StatusCode::BAD_REQUEST, "invalid_query"),
+        ];
+        for (request, status, code) in requests {
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", code);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+            assert_eq!(json_body(response).await["code"], code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replace_requires_every_field() {
+        let app = app(test_state(lazy_pool(), test_config()));
+        let id = Uuid::new_v4();
+        let admin = token(&Uuid::new_v4().to_string(), Some("admin"));
+        let user = token(&id.to_string(), None);
+        let body = json!({ "username": "ada", "full_name": null, "avatar_url": null, "bio": null, "locale": null, "metadata": null });
+        for (uri, token) in [("/api/profiles/me".to_string(), &user), (format!("/api/profiles/{}", id), &admin)] {
+            let response = app.clone().oneshot(authed("PUT", &uri, token, Some(body.clone()))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+            let problem = json_body(response).await;
+            assert_eq!(problem["code"], "validation_failed");
+            assert!(problem["detail"].as_str().unwrap().contains("missing field `timezone`"), "{}", problem);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_writes_are_audited() {
+        let Some(pool) = test_pool().await else { return };
+        let id = test_user(&pool).await;
+        let payload = CreateProfilePayload { full_name: Some("Ada".to_string()), ..Default::default() };
+        profile_repository::create_profile(&pool, id, None, payload).await.unwrap();
+        let app = app(test_state(pool.clone(), test_config()));
+        let admin_id = Uuid::new_v4();
+        let admin = token(&admin_id.to_string(), Some("admin"));
+        let uri = format!("/api/profiles/{}", id);
+
+        let replacement = json!({
+            "username": null, "full_name": "Ada Lovelace", "avatar_url": null, "bio": null,
+            "locale": null, "timezone": null, "metadata": null,
+        });
+        let response = app.clone().oneshot(authed("PUT", &uri, &admin, Some(replacement))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["full_name"], "Ada Lovelace");
+
+        let response = app.clone().oneshot(authed("PATCH", &uri, &admin, Some(json!({ "bio": "Analyst" })))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // A patch that changes nothing is not logged.
+        let response = app.clone().oneshot(authed("PATCH", &uri, &admin, Some(json!({ "bio": "Analyst" })))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(authed("DELETE", &uri, &admin, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app.oneshot(authed("GET", &uri, &admin, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let log: Vec<(Uuid, String, serde_json::Value)> = sqlx::query_as(
+            "SELECT actor_id, action, changes FROM public.profile_audit_log WHERE profile_id = $1 ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let actions: Vec<&str> = log.iter().map(|(_, action, _)| action.as_str()).collect();
+        assert_eq!(actions, ["update", "patch", "delete"]);
+        assert!(log.iter().all(|(actor, _, _)| *actor == admin_id));
+        assert_eq!(log[0].2, json!({ "full_name": { "old": "Ada", "new": "Ada Lovelace" } }));
+        assert_eq!(log[1].2, json!({ "bio": { "old": null, "new": "Analyst" } }));
+        assert_eq!(log[2].2["full_name"], json!({ "old": "Ada Lovelace", "new": null }));
+    }
+
+    async fn availability(app: &Router, token: &str, name: &str) -> serde_json::Value {
+        let uri = format!("/api/profiles/username-available?name={}", name);
+        let response = app.clone().oneshot(authed("GET", &uri, token, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        json_body(response).await
+    }
+
+    #[tokio::test]
+    async fn test_username_availability_explains_invalid_names() {
+        // Invalid names are answered without a database lookup.
+        let app = app(test_state(lazy_pool(), test_config()));
+        let user = token(&Uuid::new_v4().to_string(), None);
+        let cases = [
+            ("ab", "must be 3 to 30 characters"),
+            ("ada-lovelace", "may only contain letters, digits and underscores"),
+            ("1ada", "must start with a letter"),
+            ("Admin", "'Admin' is reserved"),
+        ];
+        for (name, reason) in cases {
+            let body = availability(&app, &user, name).await;
+            assert_eq!(body, json!({ "name": name, "available": false, "reason": reason }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_username_availability_ignores_case_and_own_name() {
+        let Some(pool) = test_pool().await else { return };
+        let owner = test_user(&pool).await;
+        let username = format!("Q{}", &Uuid::new_v4().simple().to_string()[..10]);
+        let payload = CreateProfilePayload { username: Some(username.clone()), ..Default::default() };
+        profile_repository::create_profile(&pool, owner, None, payload).await.unwrap();
+        let app = app(test_state(pool, test_config()));
+
+        let other = token(&Uuid::new_v4().to_string(), None);
+        let body = availability(&app, &other, &username.to_lowercase()).await;
+        assert_eq!(body["reason"], "is already taken");
+        assert_eq!(body["available"], false);
+
+        let body = availability(&app, &token(&owner.to_string(), None), &username.to_lowercase()).await;
+        assert_eq!(body, json!({ "name": username.to_lowercase(), "available": true }));
+    }
+
+    #[tokio::test]
+    async fn test_username_taken_in_another_case_is_a_conflict() {
+        let Some(pool) = test_pool().await else { return };
+        let username = format!("q{}", &Uuid::new_v4().simple().to_string()[..10]);
+        let payload = CreateProfilePayload { username: Some(username.clone()), ..Default::default() };
+        profile_repository::create_profile(&pool, test_user(&pool).await, None, payload).await.unwrap();
+        let second = test_user(&pool).await;
+        let app = app(test_state(pool, test_config()));
+
+        let body = json!({ "username": username.to_uppercase() });
+        let response = app.oneshot(authed("POST", "/api/profiles/me", &token(&second.to_string(), None), Some(body))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = json_body(response).await;
+        assert_eq!((&body["code"], &body["field"]), (&json!("already_taken"), &json!("username")));
+    }
+}
@@ -0,0 +1,163 @@
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::state::AppState;
+use crate::webhooks::event::DatabaseEvent;
+use crate::webhooks::registry::WebhookRegistry;
+use crate::webhooks::{SignatureScheme, WebhookError, WebhookVerifier};
+
+#[derive(Clone)]
+pub struct WebhookState {
+    pub registry: Arc<WebhookRegistry>,
+    pub verifier: Arc<WebhookVerifier>,
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            WebhookError::Signature(_) => StatusCode::UNAUTHORIZED,
+            WebhookError::Replayed(_) => StatusCode::CONFLICT,
+            WebhookError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            WebhookError::UnknownHook(_) => StatusCode::NOT_FOUND,
+            WebhookError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let message = match &self {
+            WebhookError::Db(e) => {
+                tracing::error!(error = %e, "Webhook handling failed");
+                "Webhook handler failed".to_string()
+            }
+            _ => self.to_string(),
+        };
+        // Auth hooks expect this error shape; database webhook senders only look at the status.
+        let body = json!({ "error": { "http_code": status.as_u16(), "message": message } });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Inbound webhooks from Supabase. Mounted outside `/api`: requests are authenticated
+/// by their signature, not by a user JWT.
+pub fn webhook_routes(registry: WebhookRegistry, verifier: WebhookVerifier) -> Router<AppState> {
+    Router::new()
+        .route("/database", post(database_webhook_handler))
+        .route("/auth/:hook", post(auth_hook_handler))
+        .with_state(WebhookState {
+            registry: Arc::new(registry),
+            verifier: Arc::new(verifier),
+        })
+}
+
+/// Receives database webhooks (INSERT/UPDATE/DELETE) and dispatches them by table.
+#[utoipa::path(
+    post,
+    path = "/webhooks/database",
+    tag = "webhooks",
+    request_body(content = Object, description = "Database webhook event (`type`, `table`, `schema`, `record`, `old_record`)"),
+    responses(
+        (status = 202, description = "Accepted, but no handler is registered for the table"),
+        (status = 204, description = "Handled"),
+        (status = 400, description = "Malformed payload"),
+        (status = 401, description = "Missing or invalid signature"),
+        (status = 409, description = "Replayed delivery"),
+    ),
+    security(("standard_webhooks" = []), ("webhook_signature" = []))
+)]
+async fn database_webhook_handler(
+    State(state): State<WebhookState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, WebhookError> {
+    let delivery = state.verifier.verify(SignatureScheme::Any, &headers, &body)?;
+
+    let event: DatabaseEvent<Value> =
+        serde_json::from_slice(&body).map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    let handled = state.registry.dispatch_database(event).await?;
+    delivery.complete();
+
+    // 202 tells the sender the event was accepted even though nothing handles it yet.
+    Ok(if handled == 0 { StatusCode::ACCEPTED } else { StatusCode::NO_CONTENT })
+}
+
+/// Receives a Supabase Auth hook and returns the registered handler's response.
+#[utoipa::path(
+    post,
+    path = "/webhooks/auth/{hook}",
+    tag = "webhooks",
+    params(("hook" = String, Path, description = "Registered auth hook name")),
+    request_body(content = Object, description = "Hook payload as sent by Supabase Auth"),
+    responses(
+        (status = 200, description = "The hook handler's response", body = Object),
+        (status = 401, description = "Missing or invalid signature"),
+        (status = 404, description = "No handler registered for `hook`"),
+        (status = 409, description = "Replayed delivery"),
+    ),
+    security(("standard_webhooks" = []))
+)]
+async fn auth_hook_handler(
+    State(state): State<WebhookState>,
+    Path(hook): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, WebhookError> {
+    let delivery = state.verifier.verify(SignatureScheme::StandardWebhooks, &headers, &body)?;
+
+    let payload: Value =
+        serde_json::from_slice(&body).map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    let response = state.registry.dispatch_auth_hook(&hook, payload).await?;
+    delivery.complete();
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    use crate::db::DbError;
+    use crate::test_support::{lazy_pool, test_config};
+    use crate::webhooks::signature::{standard_webhook_headers, DEFAULT_TOLERANCE};
+
+    fn hook_request(id: &str) -> Request<Body> {
+        let body = br#"{"user_id":"abc"}"#;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut request = Request::post("/webhooks/auth/flaky").body(Body::from(&body[..])).unwrap();
+        request.headers_mut().extend(standard_webhook_headers(b"secret", id, now, body));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_auth_hook_retry_after_failure_is_handled() {
+        let failed_once = Arc::new(AtomicBool::new(false));
+        let registry = WebhookRegistry::new().on_auth_hook("flaky", move |_payload| {
+            let failed_once = failed_once.clone();
+            async move {
+                if failed_once.swap(true, Ordering::SeqCst) {
+                    Ok(json!({ "ok": true }))
+                } else {
+                    Err(WebhookError::Db(DbError::TransactionUnavailable("database is down")))
+                }
+            }
+        });
+        let verifier = WebhookVerifier::new(b"secret".to_vec(), DEFAULT_TOLERANCE);
+        let app = Router::new()
+            .nest("/webhooks", webhook_routes(registry, verifier))
+            .with_state(AppState::new(lazy_pool(), test_config()));
+
+        let response = app.clone().oneshot(hook_request("msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let response = app.clone().oneshot(hook_request("msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(hook_request("msg_1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,268 @@
+use axum::{extract::ConnectInfo, http::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use hyper_util::service::TowerToHyperService;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::config::BindAddr;
+
+pub mod tls;
+
+/// A slow or stalled TLS client must not hold a connection slot (or delay shutdown) forever.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A bound socket for the API.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+enum Stream {
+    Tcp(TcpStream, SocketAddr),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+impl Listener {
+    /// Binds one address. A stale Unix socket left by a previous run is replaced;
+    /// any other file at that path is left alone and fails the bind.
+    pub async fn bind(addr: &BindAddr) -> std::io::Result<Self> {
+        match addr {
+            BindAddr::Tcp(addr) => Ok(Listener::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            BindAddr::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(Listener::Unix(tokio::net::UnixListener::bind(path)?, path.clone()))
+            }
+            #[cfg(not(unix))]
+            BindAddr::Unix(_) => {
+                Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Unix sockets are not supported on this platform"))
+            }
+        }
+    }
+
+    async fn accept(&self) -> std::io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok(Stream::Tcp(stream, addr))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => Ok(Stream::Unix(listener.accept().await?.0)),
+        }
+    }
+
+    fn unix_path(&self) -> Option<PathBuf> {
+        match self {
+            Listener::Tcp(_) => None,
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Some(path.clone()),
+        }
+    }
+
+    /// Accepts connections until aborted or the receiving side goes away. Accept errors
+    /// (such as running out of file descriptors) are logged and retried after a short pause.
+    async fn accept_into(self, connections: mpsc::Sender<Stream>) {
+        loop {
+            match self.accept().await {
+                Ok(stream) => {
+                    if connections.send(stream).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to accept connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+}
+
+/// Serves `app` on every listener until `stop_accepting` completes, then closes the
+/// listeners and waits up to `drain_timeout` for in-flight connections to finish;
+/// connections still open after that are aborted. TCP connections use TLS when an
+/// acceptor is given; Unix sockets are always plaintext. TCP connections carry the
+/// peer address as `ConnectInfo<SocketAddr>`; Unix socket peers have none.
+pub async fn serve(
+    listeners: Vec<Listener>,
+    app: Router,
+    tls: Option<TlsAcceptor>,
+    stop_accepting: impl Future<Output = ()>,
+    drain_timeout: Duration,
+) {
+    let (sender, mut connections) = mpsc::channel(64);
+    let socket_paths: Vec<PathBuf> = listeners.iter().filter_map(Listener::unix_path).collect();
+    let mut accept_tasks = JoinSet::new();
+    for listener in listeners {
+        accept_tasks.spawn(listener.accept_into(sender.clone()));
+    }
+    drop(sender);
+
+    let graceful = GracefulShutdown::new();
+    // Kept in a set so connections still open at the drain deadline can be aborted.
+    let mut connection_tasks = JoinSet::new();
+    tokio::pin!(stop_accepting);
+    loop {
+        tokio::select! {
+            stream = connections.recv() => match stream {
+                Some(stream) => {
+                    connection_tasks.spawn(serve_connection(stream, app.clone(), tls.clone(), graceful.watcher()));
+                }
+                None => break,
+            },
+            // Reap finished connections so the set does not grow.
+            Some(_) = connection_tasks.join_next(), if !connection_tasks.is_empty() => {}
+            _ = &mut stop_accepting => break,
+        }
+    }
+
+    // Aborting the accept loops drops, and so closes, the listening sockets.
+    accept_tasks.shutdown().await;
+    for path in socket_paths {
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to remove Unix socket");
+        }
+    }
+
+    tracing::info!(connections = graceful.count(), "Waiting for open connections to finish");
+    if tokio::time::timeout(drain_timeout, graceful.shutdown()).await.is_err() {
+        tracing::warn!(connections = connection_tasks.len(), "Drain deadline reached; aborting open connections");
+        connection_tasks.shutdown().await;
+    }
+}
+
+async fn serve_connection(stream: Stream, app: Router, tls: Option<TlsAcceptor>, watcher: Watcher) {
+    match stream {
+        Stream::Tcp(stream, addr) => {
+            let app = app.map_request(move |mut req: Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(addr));
+                req
+            });
+            match tls {
+                Some(tls) => match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => run(TokioIo::new(stream), app, watcher).await,
+                    Ok(Err(e)) => tracing::debug!(%addr, error = %e, "TLS handshake failed"),
+                    Err(_) => tracing::debug!(%addr, "TLS handshake timed out"),
+                },
+                None => run(TokioIo::new(stream), app, watcher).await,
+            }
+        }
+        #[cfg(unix)]
+        Stream::Unix(stream) => run(TokioIo::new(stream), app, watcher).await,
+    }
+}
+
+/// Serves HTTP/1.1 or HTTP/2 (negotiated per connection) until the peer disconnects
+/// or a graceful shutdown completes the in-flight requests.
+async fn run<I, S>(io: I, app: S, watcher: Watcher)
+where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+    S: tower::Service<Request<Incoming>, Response = axum::response::Response, Error = std::convert::Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let builder = Builder::new(TokioExecutor::new());
+    let connection = builder.serve_connection_with_upgrades(io, TowerToHyperService::new(app));
+    if let Err(e) = watcher.watch(connection.into_owned()).await {
+        tracing::debug!(error = %e, "Connection closed with error");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    async fn peer(connect_info: Option<ConnectInfo<SocketAddr>>) -> String {
+        connect_info.map_or("none".to_string(), |ConnectInfo(addr)| addr.ip().to_string())
+    }
+
+    async fn get_peer(mut stream: impl AsyncRead + AsyncWrite + Unpin) -> String {
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.rsplit("\r\n\r\n").next().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_serves_tcp_and_unix_until_stopped() {
+        let socket = std::env::temp_dir().join(format!("supabase-axum-{}.sock", std::process::id()));
+        let tcp = Listener::bind(&BindAddr::Tcp("127.0.0.1:0".parse().unwrap())).await.unwrap();
+        let Listener::Tcp(inner) = &tcp else { unreachable!() };
+        let tcp_addr = inner.local_addr().unwrap();
+        let unix = Listener::bind(&BindAddr::Unix(socket.clone())).await.unwrap();
+
+        let app = Router::new().route("/", get(peer));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let stop_accepting = async {
+            let _ = stopped.await;
+        };
+        let server = tokio::spawn(serve(vec![tcp, unix], app, None, stop_accepting, Duration::from_secs(5)));
+
+        assert_eq!(get_peer(TcpStream::connect(tcp_addr).await.unwrap()).await, "127.0.0.1");
+        assert_eq!(get_peer(tokio::net::UnixStream::connect(&socket).await.unwrap()).await, "none");
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(!socket.exists());
+        assert!(TcpStream::connect(tcp_addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aborts_connections_still_open_at_the_drain_deadline() {
+        let tcp = Listener::bind(&BindAddr::Tcp("127.0.0.1:0".parse().unwrap())).await.unwrap();
+        let Listener::Tcp(inner) = &tcp else { unreachable!() };
+        let tcp_addr = inner.local_addr().unwrap();
+
+        // The handler never finishes; `dropped` resolves once its future is dropped.
+        let (started, handler_started) = tokio::sync::oneshot::channel::<()>();
+        let (alive, dropped) = tokio::sync::oneshot::channel::<()>();
+        let handler = Arc::new(Mutex::new(Some((started, alive))));
+        let app = Router::new().route(
+            "/",
+            get(move || {
+                let (started, alive) = handler.lock().unwrap().take().unwrap();
+                async move {
+                    let _alive = alive;
+                    let _ = started.send(());
+                    std::future::pending::<()>().await
+                }
+            }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let stop_accepting = async {
+            let _ = stopped.await;
+        };
+        let server = tokio::spawn(serve(vec![tcp], app, None, stop_accepting, Duration::from_millis(100)));
+
+        let mut client = TcpStream::connect(tcp_addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\nHost: test\r\n\r\n").await.unwrap();
+        handler_started.await.unwrap();
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(dropped.await.is_err());
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response)).await.unwrap();
+        assert!(read.is_err() || response.is_empty(), "{:?}", String::from_utf8_lossy(&response));
+    }
+}
@@ -0,0 +1,160 @@
+use rustls::crypto::ring;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::TlsConfig;
+use crate::shutdown::Shutdown;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Could not read {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("No certificates found in {}", .0.display())]
+    NoCertificates(PathBuf),
+    #[error("No private key found in {}", .0.display())]
+    NoPrivateKey(PathBuf),
+    #[error("Invalid certificate or key: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// Serves whichever certificate was loaded last, so a reload applies to new
+/// handshakes without touching established connections.
+#[derive(Debug)]
+struct ReloadingCert {
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadingCert {
+    fn set(&self, key: CertifiedKey) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(key);
+    }
+}
+
+impl ResolvesServerCert for ReloadingCert {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+}
+
+/// Reads a PEM certificate chain and private key and checks that they belong together.
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, TlsError> {
+    let open = |path: &Path| {
+        File::open(path).map(BufReader::new).map_err(|source| TlsError::Io { path: path.to_path_buf(), source })
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Io { path: cert_path.to_path_buf(), source })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(cert_path.to_path_buf()));
+    }
+    let key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .map_err(|source| TlsError::Io { path: key_path.to_path_buf(), source })?
+        .ok_or_else(|| TlsError::NoPrivateKey(key_path.to_path_buf()))?;
+
+    let certified = CertifiedKey::new(certs, ring::sign::any_supported_type(&key)?);
+    certified.keys_match()?;
+    Ok(certified)
+}
+
+/// Loads the certificate and builds the acceptor for the TCP listeners, or returns
+/// `None` when TLS is not configured. A bad certificate fails startup; afterwards the
+/// files are polled and reloaded until `shutdown` is triggered.
+pub fn init(config: &TlsConfig, shutdown: &Shutdown) -> Result<Option<TlsAcceptor>, TlsError> {
+    let (Some(cert_path), Some(key_path)) = (&config.cert_path, &config.key_path) else {
+        return Ok(None);
+    };
+
+    let resolver = Arc::new(ReloadingCert { current: RwLock::new(Arc::new(load_certified_key(cert_path, key_path)?)) });
+    let mut server_config = rustls::ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver.clone());
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    tracing::info!(cert = %cert_path.display(), "Loaded TLS certificate");
+
+    let watch = watch_certificate(resolver, cert_path.clone(), key_path.clone(), config.reload_interval());
+    tokio::spawn(shutdown.clone().run_until(watch));
+    Ok(Some(TlsAcceptor::from(Arc::new(server_config))))
+}
+
+/// Background task: polls both files' modification times every `interval` and
+/// installs the new certificate once it loads cleanly. Certificate and key are often
+/// replaced one after the other, so a mismatched pair is retried on the next poll.
+async fn watch_certificate(resolver: Arc<ReloadingCert>, cert_path: PathBuf, key_path: PathBuf, interval: Duration) {
+    let modified = |path: &Path| -> Option<SystemTime> { std::fs::metadata(path).and_then(|m| m.modified()).ok() };
+    let stamp = || (modified(&cert_path), modified(&key_path));
+
+    let mut last_loaded = stamp();
+    loop {
+        tokio::time::sleep(interval).await;
+        let current = stamp();
+        if current == last_loaded {
+            continue;
+        }
+
+        match load_certified_key(&cert_path, &key_path) {
+            Ok(key) => {
+                resolver.set(key);
+                last_loaded = current;
+                tracing::info!(cert = %cert_path.display(), "Reloaded TLS certificate");
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to reload TLS certificate, keeping previous one"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Self-signed P-256 certificates for `localhost`; `a` and `b` have different keys.
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata").join(name)
+    }
+
+    #[test]
+    fn test_load_certified_key_checks_the_pair() {
+        let key = load_certified_key(&fixture("a.crt"), &fixture("a.key")).unwrap();
+        assert_eq!(key.cert.len(), 1);
+
+        assert!(matches!(load_certified_key(&fixture("a.crt"), &fixture("b.key")), Err(TlsError::Rustls(_))));
+        assert!(matches!(load_certified_key(&fixture("a.key"), &fixture("a.key")), Err(TlsError::NoCertificates(_))));
+        assert!(matches!(load_certified_key(&fixture("a.crt"), &fixture("a.crt")), Err(TlsError::NoPrivateKey(_))));
+        assert!(matches!(load_certified_key(&fixture("missing.crt"), &fixture("a.key")), Err(TlsError::Io { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_watch_certificate_reloads_matching_pairs_only() {
+        let dir = std::env::temp_dir().join(format!("supabase-axum-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("tls.crt"), dir.join("tls.key"));
+        std::fs::copy(fixture("a.crt"), &cert_path).unwrap();
+        std::fs::copy(fixture("a.key"), &key_path).unwrap();
+
+        let a = load_certified_key(&cert_path, &key_path).unwrap();
+        let b = load_certified_key(&fixture("b.crt"), &fixture("b.key")).unwrap();
+        let resolver = Arc::new(ReloadingCert { current: RwLock::new(Arc::new(a.clone())) });
+        let current = || resolver.current.read().unwrap().cert[0].clone();
+        let watch = tokio::spawn(watch_certificate(resolver.clone(), cert_path.clone(), key_path.clone(), Duration::from_millis(20)));
+        let settle = || tokio::time::sleep(Duration::from_millis(200));
+
+        // Only the certificate has been replaced: the pair does not match, so `a` stays.
+        std::fs::copy(fixture("b.crt"), &cert_path).unwrap();
+        settle().await;
+        assert_eq!(current(), a.cert[0]);
+
+        std::fs::copy(fixture("b.key"), &key_path).unwrap();
+        settle().await;
+        assert_eq!(current(), b.cert[0]);
+
+        watch.abort();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
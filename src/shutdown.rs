@@ -0,0 +1,90 @@
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Process-wide shutdown flag. Triggered once on SIGTERM/SIGINT; readiness starts
+/// failing, listeners stop accepting, and background tasks wrapped with `run_until`
+/// are stopped.
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { tx: Arc::new(watch::Sender::new(false)) }
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Completes once shutdown has been triggered (immediately if it already was).
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        // The sender lives as long as `self`, so this only returns once the flag is set.
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Runs a background task until it finishes or shutdown is triggered.
+    pub async fn run_until(self, task: impl Future<Output = ()>) {
+        tokio::select! {
+            _ = task => {}
+            _ = self.wait() => {}
+        }
+    }
+}
+
+/// Completes on SIGINT (Ctrl+C) or, on Unix, SIGTERM as sent by container orchestrators.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_trigger_stops_background_tasks() {
+        let shutdown = Shutdown::default();
+        let task = tokio::spawn(shutdown.clone().run_until(std::future::pending()));
+        assert!(!shutdown.is_triggered());
+
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(1), task).await.unwrap().unwrap();
+        // Late waiters return immediately.
+        tokio::time::timeout(Duration::from_secs(1), shutdown.wait()).await.unwrap();
+    }
+}
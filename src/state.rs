@@ -0,0 +1,139 @@
+use axum::extract::FromRef;
+use reqwest::Client;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::auth::gotrue::GoTrueAdminClient;
+use crate::auth::jwks::KeyStore;
+use crate::config::AppConfig;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+use crate::shutdown::Shutdown;
+use crate::storage::{self, Storage};
+use crate::validation::ProfileValidator;
+use crate::webhooks::signature::DEFAULT_TOLERANCE;
+use crate::webhooks::WebhookVerifier;
+
+/// Services shared by all routes and middleware. Handlers extract the parts they
+/// need (`State<PgPool>`, `State<KeyStore>`, ...) through `FromRef`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Arc<AppConfig>,
+    /// Shared HTTP client (connection pooling for JWKS fetches and the auth admin API).
+    pub http: Client,
+    pub keys: KeyStore,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub metrics: Arc<Metrics>,
+    pub shutdown: Shutdown,
+    /// Starts without a metadata schema; main installs the configured one.
+    pub profile_validator: Arc<ProfileValidator>,
+    /// Where uploaded avatars go (`storage.backend`).
+    pub storage: Arc<dyn Storage>,
+    /// Present when the auth admin API is configured.
+    pub gotrue: Option<GoTrueAdminClient>,
+    /// Checks calls to the custom access token hook; present when its secret is configured.
+    pub hook_verifier: Option<Arc<WebhookVerifier>>,
+}
+
+impl AppState {
+    /// Builds the state from a loaded config. Keys start empty; `auth::jwks::init`
+    /// fills them in.
+    pub fn new(pool: PgPool, config: AppConfig) -> Self {
+        let http = Client::new();
+        let gotrue = GoTrueAdminClient::from_config(&config.auth, http.clone());
+        let hook_verifier =
+            config.auth.hook_secret_bytes().map(|secret| Arc::new(WebhookVerifier::new(secret, DEFAULT_TOLERANCE)));
+        Self {
+            pool,
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limit.clone())),
+            profile_validator: Arc::new(ProfileValidator::new(None, config.profiles.metadata_max_bytes)),
+            storage: storage::init(&config, http.clone()),
+            config: Arc::new(config),
+            http,
+            keys: KeyStore::default(),
+            metrics: Arc::new(Metrics::new()),
+            shutdown: Shutdown::default(),
+            gotrue,
+            hook_verifier,
+        }
+    }
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AppConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for KeyStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ProfileValidator> {
+    fn from_ref(state: &AppState) -> Self {
+        state.profile_validator.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Storage> {
+    fn from_ref(state: &AppState) -> Self {
+        state.storage.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use crate::test_support::{json_body, lazy_pool, test_config, test_state, token};
+
+    fn echo_request(token: Option<&str>) -> Request<Body> {
+        let mut request = Request::post("/api/echo").header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::from(r#"{"message":"hi"}"#)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_injected_keys_authenticate_api_requests() {
+        // Never connects: the echo routes do not touch the database.
+        let state = test_state(lazy_pool(), test_config());
+        let app = Router::new().nest("/api", crate::routes::app_routes(&state)).with_state(state);
+
+        let response = app.clone().oneshot(echo_request(Some(&token("user-1", None)))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["user_id"], "user-1");
+        assert_eq!(body["echoed_message"], "hi");
+
+        let response = app.oneshot(echo_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,38 @@
+use axum::body::Bytes;
+use reqwest::{Client, StatusCode};
+use std::sync::Arc;
+
+use crate::config::{AppConfig, StorageBackend};
+
+pub mod local;
+pub mod s3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Could not write file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Network error calling the storage service: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("Storage service returned {status}: {message}")]
+    Rejected { status: StatusCode, message: String },
+}
+
+/// Object storage for uploaded files. Keys are `/`-separated relative paths
+/// chosen by the application, e.g. `avatars/<user id>/256.jpg`.
+#[axum::async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `bytes` under `key`, replacing any existing object, and returns its public URL.
+    async fn put(&self, key: &str, bytes: Bytes, content_type: &str) -> Result<String, StorageError>;
+}
+
+/// Builds the configured backend. Values are checked by `AppConfig::validate`.
+pub fn init(config: &AppConfig, http: Client) -> Arc<dyn Storage> {
+    let storage = &config.storage;
+    let public_url = storage.public_url(&config.server);
+    match storage.backend {
+        StorageBackend::Local => Arc::new(local::LocalStorage::new(storage.local_dir.clone(), public_url)),
+        StorageBackend::S3 => Arc::new(s3::S3Storage::new(http, &storage.s3, public_url)),
+    }
+}
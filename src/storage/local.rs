@@ -0,0 +1,62 @@
+use axum::body::Bytes;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{Storage, StorageError};
+
+/// Stores files under a local directory, which main serves at `/uploads`.
+/// Suitable for development and single-instance deployments.
+pub struct LocalStorage {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf, public_url: String) -> Self {
+        Self { root, public_url }
+    }
+}
+
+#[axum::async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Bytes, _content_type: &str) -> Result<String, StorageError> {
+        let path = self.root.join(key);
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // Write then rename, so readers never see a half-written file. The temporary name is
+        // unique per write, so concurrent uploads of the same key cannot publish each other's bytes.
+        let partial = self.root.join(format!("{}.{}.partial", key, Uuid::new_v4()));
+        tokio::fs::write(&partial, &bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(format!("{}/{}", self.public_url, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_writes_publish_whole_files() {
+        let root = std::env::temp_dir().join(format!("supabase-axum-storage-{}", std::process::id()));
+        let storage = std::sync::Arc::new(LocalStorage::new(root.clone(), "http://localhost:3000/uploads".to_string()));
+        let key = "avatars/user/512.jpg";
+
+        let mut writes = tokio::task::JoinSet::new();
+        for n in 0..8u8 {
+            let storage = storage.clone();
+            writes.spawn(async move { storage.put(key, Bytes::from(vec![n; 64 * 1024]), "image/jpeg").await });
+        }
+        while let Some(url) = writes.join_next().await {
+            assert_eq!(url.unwrap().unwrap(), "http://localhost:3000/uploads/avatars/user/512.jpg");
+        }
+
+        let stored = std::fs::read(root.join(key)).unwrap();
+        assert_eq!(stored.len(), 64 * 1024);
+        assert!(stored.iter().all(|byte| *byte == stored[0]));
+        // No temporary files are left next to the object.
+        assert_eq!(std::fs::read_dir(root.join("avatars/user")).unwrap().count(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
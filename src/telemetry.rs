@@ -0,0 +1,48 @@
+use axum::extract::Request;
+use tower_http::classify::{ServerErrorsAsFailures, SharedClassifier};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::{field, Level, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::auth::user_context::AuthUser;
+use crate::config::{LogFormat, LoggingConfig};
+use crate::request_id::REQUEST_ID_HEADER;
+
+/// Installs the global subscriber. `logging.filter` uses `RUST_LOG` syntax.
+pub fn init_tracing(config: &LoggingConfig) {
+    // The filter is checked by `AppConfig::validate`.
+    let filter = EnvFilter::try_new(&config.filter).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).with_span_list(false).init(),
+    }
+}
+
+/// Request span and response logging for every HTTP request. Must run inside
+/// `request_id_middleware` so the span carries the request id.
+pub fn trace_layer() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, fn(&Request) -> Span> {
+    TraceLayer::new_for_http()
+        .make_span_with(make_span as fn(&Request) -> Span)
+        .on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis))
+}
+
+fn make_span(req: &Request) -> Span {
+    let request_id = req.headers().get(&REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    tracing::info_span!(
+        "http_request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id,
+        user_id = field::Empty,
+        role = field::Empty,
+    )
+}
+
+/// Adds the authenticated user to the current request span.
+pub fn record_user(user: &AuthUser) {
+    let span = Span::current();
+    span.record("user_id", user.id.as_str());
+    span.record("role", field::display(user.role));
+}
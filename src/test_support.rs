@@ -0,0 +1,145 @@
+// Helpers shared by router and repository tests.
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, HeaderMap, Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use crate::auth::jwks::parse_jwks;
+use crate::config::AppConfig;
+use crate::state::AppState;
+
+// "secret", base64url encoded
+const JWKS: &str = r#"{"keys":[{"kty":"oct","kid":"test","alg":"HS256","k":"c2VjcmV0"}]}"#;
+
+/// Config accepting tokens from `token`.
+pub fn test_config() -> AppConfig {
+    let mut config = AppConfig::default();
+    config.auth.jwt_issuer = "https://test.supabase.co/auth/v1".to_string();
+    config.auth.jwt_audience = "authenticated".to_string();
+    config
+}
+
+/// Pool that never connects, for routes that fail or answer before touching the database.
+pub fn lazy_pool() -> PgPool {
+    PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap()
+}
+
+/// State with the test signing key installed.
+pub fn test_state(pool: PgPool, config: AppConfig) -> AppState {
+    let state = AppState::new(pool, config);
+    state.keys.install(parse_jwks(JWKS.as_bytes()).unwrap());
+    state
+}
+
+/// Access token for `sub`; `role` is put in the `user_role` claim.
+pub fn token(sub: &str, role: Option<&str>) -> String {
+    let header = Header { kid: Some("test".to_string()), ..Header::default() };
+    let mut claims = json!({
+        "sub": sub,
+        "iss": "https://test.supabase.co/auth/v1",
+        "aud": "authenticated",
+        "role": "authenticated",
+        "iat": 1_700_000_000,
+        "exp": 4_000_000_000_i64,
+    });
+    if let Some(role) = role {
+        claims["user_role"] = json!(role);
+    }
+    encode(&header, &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+}
+
+/// Request with a bearer token and, when given, a JSON body.
+pub fn authed(method: &str, uri: &str, token: &str, body: Option<Value>) -> Request<Body> {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token));
+    match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    }
+}
+
+pub async fn json_body(response: Response) -> Value {
+    serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+}
+
+/// Pool for `TEST_DATABASE_URL`, a database with `schema.sql` applied. Tests needing
+/// Postgres return early when it is unset.
+pub async fn test_pool() -> Option<PgPool> {
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    Some(PgPoolOptions::new().max_connections(4).connect(&url).await.expect("TEST_DATABASE_URL is reachable"))
+}
+
+/// Inserts an `auth.users` row for a profile to reference.
+pub async fn test_user(pool: &PgPool) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO auth.users (id) VALUES ($1)").bind(id).execute(pool).await.unwrap();
+    id
+}
+
+/// Service role key expected by `mock_gotrue`.
+pub const SERVICE_KEY: &str = "service-key";
+
+/// Stand-in for the GoTrue admin API, see `mock_gotrue`.
+pub struct MockGoTrue {
+    /// Base URL to use as `auth.supabase_url`.
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockGoTrue {
+    /// Requests received so far, as "METHOD /path?query".
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Serves the GoTrue admin endpoints on a local port. User listings carry
+/// `x-total-count: <total>` when given; created users get a fresh id.
+pub async fn mock_gotrue(total: Option<&'static str>) -> MockGoTrue {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let app = Router::new().fallback(move |method: Method, uri: Uri, headers: HeaderMap, body: String| {
+        let recorded = recorded.clone();
+        async move {
+            recorded.lock().unwrap().push(format!("{} {}", method, uri));
+            if headers.get("apikey").and_then(|v| v.to_str().ok()) != Some(SERVICE_KEY) {
+                return (StatusCode::UNAUTHORIZED, Json(json!({ "msg": "invalid api key" }))).into_response();
+            }
+            let user = |id: Uuid, email: Value| json!({ "id": id, "email": email, "created_at": "2024-01-01T00:00:00Z" });
+            match (method, uri.path()) {
+                (Method::GET, "/auth/v1/admin/users") => {
+                    let body = Json(json!({ "users": [user(Uuid::new_v4(), json!("a@example.com"))] }));
+                    match total {
+                        Some(total) => ([("x-total-count", total)], body).into_response(),
+                        None => body.into_response(),
+                    }
+                }
+                (Method::POST, "/auth/v1/admin/users") => {
+                    let payload: Value = serde_json::from_str(&body).unwrap();
+                    Json(user(Uuid::new_v4(), payload["email"].clone())).into_response()
+                }
+                (Method::DELETE, path) if path.starts_with("/auth/v1/admin/users/") => Json(json!({})).into_response(),
+                _ => (StatusCode::NOT_FOUND, Json(json!({ "msg": "User not found" }))).into_response(),
+            }
+        }
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    MockGoTrue { url, requests }
+}
@@ -0,0 +1,264 @@
+use jsonschema::Validator;
+use serde_json::Value;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::db::models::{CreateProfilePayload, Patch, ProfilePatch, UpdateProfilePayload};
+
+pub const USERNAME_MIN_CHARS: usize = 3;
+pub const USERNAME_MAX_CHARS: usize = 30;
+pub const FULL_NAME_MAX_CHARS: usize = 100;
+pub const BIO_MAX_CHARS: usize = 500;
+pub const AVATAR_URL_MAX_LEN: usize = 2048;
+/// Usernames that could pass for the service, its staff or a route. Compared case-insensitively.
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin", "administrator", "anonymous", "api", "auth", "help", "moderator", "null", "official", "owner",
+    "profiles", "root", "security", "service", "staff", "supabase", "support", "system", "undefined",
+];
+/// Longest BCP 47 tag worth supporting (RFC 5646 section 4.4.1 recommends 35).
+const LOCALE_MAX_LEN: usize = 35;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("Could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("{} is not valid JSON: {source}", path.display())]
+    Parse { path: PathBuf, source: serde_json::Error },
+    #[error("{} is not a valid JSON Schema: {message}", path.display())]
+    Invalid { path: PathBuf, message: String },
+}
+
+/// Every problem found in a request body, as `field: message`.
+#[derive(Debug, Default, thiserror::Error)]
+#[error("{}", .0.join("; "))]
+pub struct ValidationErrors(pub Vec<String>);
+
+impl ValidationErrors {
+    fn add(&mut self, field: &str, message: impl Display) {
+        self.0.push(format!("{}: {}", field, message));
+    }
+
+    fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+/// Loads and compiles the JSON Schema for profile `metadata`. Remote `$ref`s are not resolved.
+pub fn load_metadata_schema(path: &Path) -> Result<Validator, SchemaError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|source| SchemaError::Read { path: path.to_path_buf(), source })?;
+    let schema: Value =
+        serde_json::from_str(&contents).map_err(|source| SchemaError::Parse { path: path.to_path_buf(), source })?;
+    jsonschema::validator_for(&schema)
+        .map_err(|e| SchemaError::Invalid { path: path.to_path_buf(), message: e.to_string() })
+}
+
+/// Checks profile fields before they are written. The database enforces the
+/// length limits too; this reports every problem at once with readable messages.
+pub struct ProfileValidator {
+    metadata_schema: Option<Validator>,
+    metadata_max_bytes: usize,
+}
+
+impl ProfileValidator {
+    pub fn new(metadata_schema: Option<Validator>, metadata_max_bytes: usize) -> Self {
+        Self { metadata_schema, metadata_max_bytes }
+    }
+
+    pub fn check_create(&self, payload: &CreateProfilePayload) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        self.check_fields(
+            &mut errors,
+            [
+                payload.username.as_ref(),
+                payload.full_name.as_ref(),
+                payload.avatar_url.as_ref(),
+                payload.bio.as_ref(),
+                payload.locale.as_ref(),
+                payload.timezone.as_ref(),
+            ],
+            payload.metadata.as_ref(),
+        );
+        errors.into_result()
+    }
+
+    pub fn check_update(&self, payload: &UpdateProfilePayload) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        self.check_fields(
+            &mut errors,
+            [
+                payload.username.as_ref(),
+                payload.full_name.as_ref(),
+                payload.avatar_url.as_ref(),
+                payload.bio.as_ref(),
+                payload.locale.as_ref(),
+                payload.timezone.as_ref(),
+            ],
+            payload.metadata.as_ref(),
+        );
+        errors.into_result()
+    }
+
+    /// `patch.metadata` must already be merged into the stored value, so the
+    /// schema sees the object that will be written.
+    pub fn check_patch(&self, patch: &ProfilePatch) -> Result<(), ValidationErrors> {
+        fn value<T>(patch: &Patch<T>) -> Option<&T> {
+            patch.update().flatten()
+        }
+        let mut errors = ValidationErrors::default();
+        self.check_fields(
+            &mut errors,
+            [
+                value(&patch.username),
+                value(&patch.full_name),
+                value(&patch.avatar_url),
+                value(&patch.bio),
+                value(&patch.locale),
+                value(&patch.timezone),
+            ],
+            value(&patch.metadata),
+        );
+        errors.into_result()
+    }
+
+    /// `text` holds username, full_name, avatar_url, bio, locale and timezone, in that order.
+    fn check_fields(&self, errors: &mut ValidationErrors, text: [Option<&String>; 6], metadata: Option<&Value>) {
+        let [username, full_name, avatar_url, bio, locale, timezone] = text;
+        if let Some(username) = username
+            && let Err(message) = check_username(username)
+        {
+            errors.add("username", message);
+        }
+        if let Some(full_name) = full_name {
+            check_text(errors, "full_name", full_name, FULL_NAME_MAX_CHARS);
+        }
+        if let Some(bio) = bio {
+            check_text(errors, "bio", bio, BIO_MAX_CHARS);
+        }
+        if let Some(url) = avatar_url
+            && !is_avatar_url(url)
+        {
+            errors.add("avatar_url", format_args!("must be an http(s) URL of at most {} bytes", AVATAR_URL_MAX_LEN));
+        }
+        if let Some(locale) = locale
+            && !is_language_tag(locale)
+        {
+            errors.add("locale", format_args!("'{}' is not a language tag like 'en' or 'pt-BR'", locale));
+        }
+        if let Some(timezone) = timezone
+            && timezone.parse::<chrono_tz::Tz>().is_err()
+        {
+            errors.add("timezone", format_args!("'{}' is not an IANA time zone like 'Europe/Berlin'", timezone));
+        }
+        if let Some(metadata) = metadata {
+            self.check_metadata(errors, metadata);
+        }
+    }
+
+    fn check_metadata(&self, errors: &mut ValidationErrors, metadata: &Value) {
+        if !metadata.is_object() {
+            errors.add("metadata", "must be an object");
+            return;
+        }
+        let size = serde_json::to_vec(metadata).map_or(usize::MAX, |bytes| bytes.len());
+        if size > self.metadata_max_bytes {
+            errors.add("metadata", format_args!("must be at most {} bytes of JSON", self.metadata_max_bytes));
+            return;
+        }
+        if let Some(schema) = &self.metadata_schema {
+            for error in schema.iter_errors(metadata) {
+                let location = error.instance_path.as_str();
+                let field = if location.is_empty() { "metadata".to_string() } else { format!("metadata{}", location) };
+                errors.add(&field, error);
+            }
+        }
+    }
+}
+
+/// Checks the username rules: 3 to 30 ASCII letters, digits or underscores, starting
+/// with a letter, and not reserved. Uniqueness (ignoring case) is left to the database.
+pub fn check_username(username: &str) -> Result<(), String> {
+    if !(USERNAME_MIN_CHARS..=USERNAME_MAX_CHARS).contains(&username.chars().count()) {
+        return Err(format!("must be {} to {} characters", USERNAME_MIN_CHARS, USERNAME_MAX_CHARS));
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err("may only contain letters, digits and underscores".to_string());
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("must start with a letter".to_string());
+    }
+    if RESERVED_USERNAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(username)) {
+        return Err(format!("'{}' is reserved", username));
+    }
+    Ok(())
+}
+
+fn check_text(errors: &mut ValidationErrors, field: &str, value: &str, max_chars: usize) {
+    if value.trim().is_empty() {
+        errors.add(field, "must not be blank; send null to clear it");
+    } else if value.chars().count() > max_chars {
+        errors.add(field, format_args!("must be at most {} characters", max_chars));
+    }
+}
+
+fn is_avatar_url(value: &str) -> bool {
+    value.len() <= AVATAR_URL_MAX_LEN
+        && reqwest::Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// A BCP 47-shaped tag: a 2-3 letter language, then alphanumeric subtags of 1-8 characters.
+/// Whether the subtags are registered is not checked.
+fn is_language_tag(value: &str) -> bool {
+    let mut subtags = value.split('-');
+    let language = subtags.next().unwrap_or_default();
+    value.len() <= LOCALE_MAX_LEN
+        && (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reports_every_invalid_field() {
+        let schema = jsonschema::validator_for(&json!({
+            "type": "object",
+            "properties": { "theme": { "enum": ["light", "dark"] } }
+        }))
+        .unwrap();
+        let validator = ProfileValidator::new(Some(schema), 64);
+
+        let valid = CreateProfilePayload {
+            username: Some("Ada_Lovelace".to_string()),
+            full_name: Some("Ada Lovelace".to_string()),
+            avatar_url: Some("https://cdn.example.com/ada.png".to_string()),
+            locale: Some("en-GB".to_string()),
+            timezone: Some("Europe/London".to_string()),
+            metadata: Some(json!({ "theme": "dark" })),
+            ..Default::default()
+        };
+        assert!(validator.check_create(&valid).is_ok());
+
+        let invalid = CreateProfilePayload {
+            username: Some("1ada".to_string()),
+            full_name: Some(" ".to_string()),
+            avatar_url: Some("javascript:alert(1)".to_string()),
+            bio: Some("x".repeat(BIO_MAX_CHARS + 1)),
+            locale: Some("english".to_string()),
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+            metadata: Some(json!({ "theme": "neon" })),
+        };
+        let errors = validator.check_create(&invalid).unwrap_err().0;
+        let fields: Vec<&str> = errors.iter().map(|e| e.split(':').next().unwrap()).collect();
+        assert_eq!(fields, ["username", "full_name", "bio", "avatar_url", "locale", "timezone", "metadata/theme"]);
+
+        let reserved = CreateProfilePayload { username: Some("Admin".to_string()), ..Default::default() };
+        assert_eq!(validator.check_create(&reserved).unwrap_err().0, ["username: 'Admin' is reserved"]);
+
+        let oversized = CreateProfilePayload { metadata: Some(json!({ "theme": "x".repeat(64) })), ..Default::default() };
+        assert_eq!(validator.check_create(&oversized).unwrap_err().0, ["metadata: must be at most 64 bytes of JSON"]);
+    }
+}
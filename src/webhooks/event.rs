@@ -0,0 +1,97 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Payload of a Supabase database webhook. `T` is the row type; handlers registered
+/// through `WebhookRegistry::on_table` receive it already deserialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "UPPERCASE")]
+pub enum DatabaseEvent<T> {
+    Insert {
+        schema: String,
+        table: String,
+        record: T,
+    },
+    Update {
+        schema: String,
+        table: String,
+        record: T,
+        old_record: T,
+    },
+    Delete {
+        schema: String,
+        table: String,
+        old_record: T,
+    },
+}
+
+impl<T> DatabaseEvent<T> {
+    pub fn schema(&self) -> &str {
+        match self {
+            DatabaseEvent::Insert { schema, .. }
+            | DatabaseEvent::Update { schema, .. }
+            | DatabaseEvent::Delete { schema, .. } => schema,
+        }
+    }
+
+    pub fn table(&self) -> &str {
+        match self {
+            DatabaseEvent::Insert { table, .. }
+            | DatabaseEvent::Update { table, .. }
+            | DatabaseEvent::Delete { table, .. } => table,
+        }
+    }
+}
+
+impl DatabaseEvent<Value> {
+    /// Deserializes the record columns into a typed row.
+    pub fn into_typed<T: DeserializeOwned>(self) -> Result<DatabaseEvent<T>, serde_json::Error> {
+        Ok(match self {
+            DatabaseEvent::Insert { schema, table, record } => DatabaseEvent::Insert {
+                schema,
+                table,
+                record: serde_json::from_value(record)?,
+            },
+            DatabaseEvent::Update { schema, table, record, old_record } => DatabaseEvent::Update {
+                schema,
+                table,
+                record: serde_json::from_value(record)?,
+                old_record: serde_json::from_value(old_record)?,
+            },
+            DatabaseEvent::Delete { schema, table, old_record } => DatabaseEvent::Delete {
+                schema,
+                table,
+                old_record: serde_json::from_value(old_record)?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    struct Row {
+        id: i64,
+    }
+
+    #[test]
+    fn test_database_event_into_typed() {
+        let payload = json!({
+            "type": "UPDATE",
+            "schema": "public",
+            "table": "profiles",
+            "record": { "id": 2 },
+            "old_record": { "id": 1 },
+        });
+        let event: DatabaseEvent<Value> = serde_json::from_value(payload).unwrap();
+        assert_eq!((event.schema(), event.table()), ("public", "profiles"));
+
+        match event.into_typed::<Row>().unwrap() {
+            DatabaseEvent::Update { record, old_record, .. } => assert_eq!((record.id, old_record.id), (2, 1)),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
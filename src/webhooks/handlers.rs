@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::event::DatabaseEvent;
+use super::registry::WebhookRegistry;
+use crate::db::profile_repository;
+
+/// The `auth.users` columns the handlers below need.
+#[derive(Debug, Deserialize)]
+struct AuthUserRow {
+    id: Uuid,
+    email: Option<String>,
+}
+
+/// Builds the registry of application webhook handlers.
+/// Register new handlers here, e.g. `.on_table::<MyRow, _, _>("public", "orders", ...)`.
+pub fn default_registry(pool: PgPool) -> WebhookRegistry {
+    WebhookRegistry::new()
+        .on_table("auth", "users", move |event: DatabaseEvent<AuthUserRow>| {
+            let pool = pool.clone();
+            async move {
+                // Give every new sign-up a profile row. Deletes cascade from auth.users.
+                if let DatabaseEvent::Insert { record, .. } = event {
+                    profile_repository::ensure_profile(&pool, record.id, record.email).await?;
+                }
+                Ok(())
+            }
+        })
+        .on_auth_hook("password-verification-attempt", |payload: Value| async move {
+            // Supabase Auth calls this after each password check. Log failures and let
+            // GoTrue apply its default behaviour; return "reject" here to lock accounts out.
+            if payload["valid"] == Value::Bool(false) {
+                tracing::info!(user_id = %payload["user_id"], "Failed password attempt");
+            }
+            Ok(json!({ "decision": "continue" }))
+        })
+}
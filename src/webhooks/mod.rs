@@ -0,0 +1,131 @@
+use axum::http::HeaderMap;
+use std::time::Duration;
+
+pub mod event;
+pub mod handlers;
+pub mod registry;
+pub mod replay;
+pub mod signature;
+
+use crate::db::DbError;
+use replay::ReplayGuard;
+use signature::SignatureError;
+
+/// Errors raised while receiving or handling an inbound webhook.
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook signature: {0}")]
+    Signature(#[from] SignatureError),
+
+    #[error("Webhook message {0} was already received")]
+    Replayed(String),
+
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("No handler registered for auth hook {0}")]
+    UnknownHook(String),
+
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// Which signature scheme a webhook endpoint accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// Standard Webhooks (`webhook-*` headers), as used by Supabase Auth hooks.
+    StandardWebhooks,
+    /// Standard Webhooks or the plain `x-webhook-signature` HMAC scheme.
+    Any,
+}
+
+/// Verifies signatures and timestamps of inbound webhooks and rejects replays.
+pub struct WebhookVerifier {
+    secret: Vec<u8>,
+    tolerance: Duration,
+    replay: ReplayGuard,
+}
+
+impl WebhookVerifier {
+    pub fn new(secret: Vec<u8>, tolerance: Duration) -> Self {
+        // A replayed message older than the tolerance fails the timestamp check,
+        // so ids only need to be remembered for the tolerance window on either side.
+        Self { secret, tolerance, replay: ReplayGuard::new(tolerance * 2) }
+    }
+
+    /// Checks the signature and timestamp and claims the message id. Call `complete` on
+    /// the returned delivery once it is handled; otherwise the id is released again.
+    pub fn verify(&self, scheme: SignatureScheme, headers: &HeaderMap, body: &[u8]) -> Result<Delivery<'_>, WebhookError> {
+        let id = match scheme {
+            SignatureScheme::StandardWebhooks => signature::verify_standard_webhook(&self.secret, headers, body, self.tolerance)?,
+            SignatureScheme::Any => signature::verify_any(&self.secret, headers, body, self.tolerance)?,
+        };
+        if !self.replay.check_and_record(&id) {
+            return Err(WebhookError::Replayed(id));
+        }
+        Ok(Delivery { replay: &self.replay, id: Some(id) })
+    }
+}
+
+/// A verified message whose id is held while it is handled. Concurrent duplicates are
+/// rejected, but if it is dropped without `complete` (the handler failed or the request
+/// was cancelled) the id is forgotten, so the sender's retry is accepted.
+#[must_use = "call `complete` once the webhook has been handled"]
+pub struct Delivery<'a> {
+    replay: &'a ReplayGuard,
+    id: Option<String>,
+}
+
+impl Delivery<'_> {
+    /// Keeps the id recorded, so later replays are rejected.
+    pub fn complete(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for Delivery<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.replay.forget(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signature::{standard_webhook_headers, DEFAULT_TOLERANCE};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn headers(id: &str) -> HeaderMap {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        standard_webhook_headers(b"secret", id, now, b"{}")
+    }
+
+    fn verify<'a>(verifier: &'a WebhookVerifier, headers: &HeaderMap) -> Result<Delivery<'a>, WebhookError> {
+        verifier.verify(SignatureScheme::StandardWebhooks, headers, b"{}")
+    }
+
+    #[test]
+    fn test_failed_delivery_can_be_retried() {
+        let verifier = WebhookVerifier::new(b"secret".to_vec(), DEFAULT_TOLERANCE);
+        let headers = headers("msg_1");
+
+        // The handler failed: the delivery is dropped without `complete`.
+        drop(verify(&verifier, &headers).unwrap());
+        verify(&verifier, &headers).unwrap().complete();
+
+        assert!(matches!(verify(&verifier, &headers), Err(WebhookError::Replayed(id)) if id == "msg_1"));
+    }
+
+    #[test]
+    fn test_duplicate_is_rejected_while_first_is_handled() {
+        let verifier = WebhookVerifier::new(b"secret".to_vec(), DEFAULT_TOLERANCE);
+        let headers = headers("msg_2");
+
+        let first = verify(&verifier, &headers).unwrap();
+        assert!(matches!(verify(&verifier, &headers), Err(WebhookError::Replayed(_))));
+        first.complete();
+        assert!(matches!(verify(&verifier, &headers), Err(WebhookError::Replayed(_))));
+    }
+}
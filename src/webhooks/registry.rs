@@ -0,0 +1,126 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use super::event::DatabaseEvent;
+use super::WebhookError;
+
+/// Handles database webhook events for one table.
+#[axum::async_trait]
+pub trait DatabaseEventHandler: Send + Sync {
+    async fn handle(&self, event: DatabaseEvent<Value>) -> Result<(), WebhookError>;
+}
+
+/// Handles a Supabase Auth hook. The returned JSON is sent back to Supabase Auth.
+#[axum::async_trait]
+pub trait AuthHookHandler: Send + Sync {
+    async fn handle(&self, payload: Value) -> Result<Value, WebhookError>;
+}
+
+/// Adapter that deserializes the row into `T` before calling a closure.
+struct TypedTableHandler<T, F> {
+    handler: F,
+    _row: PhantomData<fn() -> T>,
+}
+
+#[axum::async_trait]
+impl<T, F, Fut> DatabaseEventHandler for TypedTableHandler<T, F>
+where
+    T: DeserializeOwned + Send + 'static,
+    F: Fn(DatabaseEvent<T>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), WebhookError>> + Send,
+{
+    async fn handle(&self, event: DatabaseEvent<Value>) -> Result<(), WebhookError> {
+        let event = event
+            .into_typed::<T>()
+            .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+        (self.handler)(event).await
+    }
+}
+
+struct FnAuthHookHandler<F>(F);
+
+#[axum::async_trait]
+impl<F, Fut> AuthHookHandler for FnAuthHookHandler<F>
+where
+    F: Fn(Value) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Value, WebhookError>> + Send,
+{
+    async fn handle(&self, payload: Value) -> Result<Value, WebhookError> {
+        (self.0)(payload).await
+    }
+}
+
+/// Maps incoming webhooks to the Rust handlers registered for them.
+#[derive(Default)]
+pub struct WebhookRegistry {
+    tables: HashMap<(String, String), Vec<Arc<dyn DatabaseEventHandler>>>,
+    auth_hooks: HashMap<String, Arc<dyn AuthHookHandler>>,
+}
+
+impl WebhookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a closure for INSERT/UPDATE/DELETE events on `schema.table`,
+    /// receiving rows deserialized into `T`. Several handlers may share a table.
+    pub fn on_table<T, F, Fut>(self, schema: &str, table: &str, handler: F) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(DatabaseEvent<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), WebhookError>> + Send + 'static,
+    {
+        self.on_table_handler(schema, table, TypedTableHandler { handler, _row: PhantomData })
+    }
+
+    pub fn on_table_handler(mut self, schema: &str, table: &str, handler: impl DatabaseEventHandler + 'static) -> Self {
+        self.tables
+            .entry((schema.to_string(), table.to_string()))
+            .or_default()
+            .push(Arc::new(handler));
+        self
+    }
+
+    /// Registers the handler for the auth hook called `name` (`POST /webhooks/auth/{name}`).
+    pub fn on_auth_hook<F, Fut>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, WebhookError>> + Send + 'static,
+    {
+        self.auth_hooks.insert(name.to_string(), Arc::new(FnAuthHookHandler(handler)));
+        self
+    }
+
+    /// Runs every handler registered for the event's table and returns how many ran.
+    /// All handlers run even if one fails; the first error is returned.
+    pub async fn dispatch_database(&self, event: DatabaseEvent<Value>) -> Result<usize, WebhookError> {
+        let key = (event.schema().to_string(), event.table().to_string());
+        let Some(handlers) = self.tables.get(&key) else {
+            return Ok(0);
+        };
+
+        let mut first_error = None;
+        for handler in handlers {
+            if let Err(e) = handler.handle(event.clone()).await {
+                tracing::error!(schema = %key.0, table = %key.1, error = %e, "Webhook handler failed");
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(handlers.len()),
+        }
+    }
+
+    pub async fn dispatch_auth_hook(&self, name: &str, payload: Value) -> Result<Value, WebhookError> {
+        let handler = self
+            .auth_hooks
+            .get(name)
+            .ok_or_else(|| WebhookError::UnknownHook(name.to_string()))?;
+        handler.handle(payload).await
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Remembers recently seen webhook message ids so a captured request cannot be replayed.
+/// Ids only need to be kept for as long as their timestamp would still be accepted.
+pub struct ReplayGuard {
+    ttl: Duration,
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl ReplayGuard {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, seen: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records `id` and returns `true` if it had not been seen within the TTL.
+    pub fn check_and_record(&self, id: &str) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, expires_at| *expires_at > now);
+        if seen.contains_key(id) {
+            return false;
+        }
+        seen.insert(id.to_string(), now + self.ttl);
+        true
+    }
+
+    /// Forgets `id`, so a delivery that could not be handled is accepted when retried.
+    pub fn forget(&self, id: &str) {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+    }
+}
@@ -0,0 +1,212 @@
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How far a webhook timestamp may drift from the local clock before the request is rejected.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("Webhook secret is not a valid `v1,whsec_<base64>` value")]
+    InvalidSecret,
+
+    #[error("Missing webhook header: {0}")]
+    MissingHeader(&'static str),
+
+    #[error("Invalid webhook timestamp")]
+    InvalidTimestamp,
+
+    #[error("Webhook timestamp outside the allowed tolerance")]
+    TimestampOutOfTolerance,
+
+    #[error("No matching webhook signature")]
+    SignatureMismatch,
+}
+
+/// Decodes a Standard Webhooks secret as shown in the Supabase dashboard
+/// (`v1,whsec_<base64>`). The `v1,` and `whsec_` prefixes are optional.
+pub fn decode_secret(secret: &str) -> Result<Vec<u8>, SignatureError> {
+    let secret = secret.trim();
+    let secret = secret.strip_prefix("v1,").unwrap_or(secret);
+    let secret = secret.strip_prefix("whsec_").unwrap_or(secret);
+    STANDARD.decode(secret).map_err(|_| SignatureError::InvalidSecret)
+}
+
+/// Parses a shared webhook secret: `whsec_` values are base64 decoded, anything
+/// else is used verbatim as the HMAC key.
+pub fn parse_secret(secret: &str) -> Result<Vec<u8>, SignatureError> {
+    let trimmed = secret.trim();
+    if trimmed.starts_with("v1,whsec_") || trimmed.starts_with("whsec_") {
+        decode_secret(trimmed)
+    } else if trimmed.is_empty() {
+        Err(SignatureError::InvalidSecret)
+    } else {
+        Ok(trimmed.as_bytes().to_vec())
+    }
+}
+
+/// Verifies a request signed according to the Standard Webhooks specification:
+/// `webhook-signature` holds one or more space separated `v1,<base64 HMAC-SHA256>`
+/// entries over `"{webhook-id}.{webhook-timestamp}.{body}"`.
+/// Returns the `webhook-id`, which identifies the message for replay protection.
+pub fn verify_standard_webhook(secret: &[u8], headers: &HeaderMap, body: &[u8], tolerance: Duration) -> Result<String, SignatureError> {
+    let id = header(headers, "webhook-id")?;
+    let timestamp = header(headers, "webhook-timestamp")?;
+    let signatures = header(headers, "webhook-signature")?;
+    check_timestamp(timestamp, tolerance)?;
+
+    for candidate in signatures.split(' ') {
+        let Some(encoded) = candidate.strip_prefix("v1,") else { continue };
+        let Ok(expected) = STANDARD.decode(encoded) else { continue };
+        let mac = signed_mac(secret, &[id.as_bytes(), b".", timestamp.as_bytes(), b".", body])?;
+        // `verify_slice` compares in constant time.
+        if mac.verify_slice(&expected).is_ok() {
+            return Ok(id.to_string());
+        }
+    }
+
+    Err(SignatureError::SignatureMismatch)
+}
+
+/// Verifies the plain HMAC scheme used for database webhooks, where the sender
+/// (e.g. a `pg_net` trigger using pgcrypto's `hmac()`) sets:
+/// - `x-webhook-timestamp`: Unix seconds
+/// - `x-webhook-signature`: `sha256=<hex HMAC-SHA256 of "{timestamp}.{body}">`
+/// - `x-webhook-id` (optional): unique message id
+///
+/// Returns the message id, falling back to the signature itself when no id is sent.
+pub fn verify_hmac_sha256(secret: &[u8], headers: &HeaderMap, body: &[u8], tolerance: Duration) -> Result<String, SignatureError> {
+    let timestamp = header(headers, "x-webhook-timestamp")?;
+    let signature = header(headers, "x-webhook-signature")?;
+    check_timestamp(timestamp, tolerance)?;
+
+    let encoded = signature.strip_prefix("sha256=").unwrap_or(signature);
+    let expected = hex::decode(encoded).map_err(|_| SignatureError::SignatureMismatch)?;
+    let mac = signed_mac(secret, &[timestamp.as_bytes(), b".", body])?;
+    mac.verify_slice(&expected).map_err(|_| SignatureError::SignatureMismatch)?;
+
+    let id = headers
+        .get("x-webhook-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(encoded);
+    Ok(id.to_string())
+}
+
+/// Verifies whichever supported scheme the request carries headers for.
+pub fn verify_any(secret: &[u8], headers: &HeaderMap, body: &[u8], tolerance: Duration) -> Result<String, SignatureError> {
+    if headers.contains_key("webhook-signature") {
+        verify_standard_webhook(secret, headers, body, tolerance)
+    } else {
+        verify_hmac_sha256(secret, headers, body, tolerance)
+    }
+}
+
+fn signed_mac(secret: &[u8], parts: &[&[u8]]) -> Result<HmacSha256, SignatureError> {
+    let mut mac = HmacSha256::new_from_slice(secret).map_err(|_| SignatureError::InvalidSecret)?;
+    for part in parts {
+        mac.update(part);
+    }
+    Ok(mac)
+}
+
+fn check_timestamp(timestamp: &str, tolerance: Duration) -> Result<(), SignatureError> {
+    let sent_at: u64 = timestamp.parse().map_err(|_| SignatureError::InvalidTimestamp)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| SignatureError::InvalidTimestamp)?
+        .as_secs();
+    if now.abs_diff(sent_at) > tolerance.as_secs() {
+        return Err(SignatureError::TimestampOutOfTolerance);
+    }
+    Ok(())
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, SignatureError> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(SignatureError::MissingHeader(name))
+}
+
+/// Standard Webhooks headers signing `body` as message `id`, sent at `timestamp`.
+#[cfg(test)]
+pub(crate) fn standard_webhook_headers(secret: &[u8], id: &str, timestamp: u64, body: &[u8]) -> HeaderMap {
+    use axum::http::HeaderValue;
+
+    let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+    mac.update(format!("{}.{}.", id, timestamp).as_bytes());
+    mac.update(body);
+    let signature = format!("v1,{}", STANDARD.encode(mac.finalize().into_bytes()));
+
+    let mut headers = HeaderMap::new();
+    headers.insert("webhook-id", HeaderValue::from_str(id).unwrap());
+    headers.insert("webhook-timestamp", HeaderValue::from_str(&timestamp.to_string()).unwrap());
+    headers.insert("webhook-signature", HeaderValue::from_str(&signature).unwrap());
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn signed_headers(secret: &[u8], timestamp: u64, body: &[u8]) -> HeaderMap {
+        standard_webhook_headers(secret, "msg_1", timestamp, body)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn test_decode_secret_strips_prefixes() {
+        let encoded = format!("v1,whsec_{}", STANDARD.encode(b"secret"));
+        assert_eq!(decode_secret(&encoded).unwrap(), b"secret");
+        assert_eq!(decode_secret("v1,whsec_***"), Err(SignatureError::InvalidSecret));
+        assert_eq!(parse_secret("plain-secret").unwrap(), b"plain-secret");
+    }
+
+    #[test]
+    fn test_verify_accepts_valid_signature() {
+        let body = br#"{"user_id":"abc"}"#;
+        let headers = signed_headers(b"secret", now(), body);
+        assert_eq!(verify_standard_webhook(b"secret", &headers, body, DEFAULT_TOLERANCE), Ok("msg_1".to_string()));
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_body_and_wrong_secret() {
+        let body = br#"{"user_id":"abc"}"#;
+        let headers = signed_headers(b"secret", now(), body);
+        assert_eq!(verify_standard_webhook(b"secret", &headers, b"{}", DEFAULT_TOLERANCE), Err(SignatureError::SignatureMismatch));
+        assert_eq!(verify_standard_webhook(b"other", &headers, body, DEFAULT_TOLERANCE), Err(SignatureError::SignatureMismatch));
+    }
+
+    #[test]
+    fn test_verify_rejects_stale_timestamp() {
+        let body = b"{}";
+        let headers = signed_headers(b"secret", now() - 3600, body);
+        assert_eq!(verify_standard_webhook(b"secret", &headers, body, DEFAULT_TOLERANCE), Err(SignatureError::TimestampOutOfTolerance));
+    }
+
+    #[test]
+    fn test_verify_hmac_sha256() {
+        let body = br#"{"type":"INSERT"}"#;
+        let timestamp = now().to_string();
+        let mut mac = HmacSha256::new_from_slice(b"secret").unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-webhook-timestamp", HeaderValue::from_str(&timestamp).unwrap());
+        headers.insert("x-webhook-signature", HeaderValue::from_str(&signature).unwrap());
+        headers.insert("x-webhook-id", HeaderValue::from_static("evt_1"));
+
+        assert_eq!(verify_any(b"secret", &headers, body, DEFAULT_TOLERANCE), Ok("evt_1".to_string()));
+        assert_eq!(verify_any(b"secret", &headers, b"{}", DEFAULT_TOLERANCE), Err(SignatureError::SignatureMismatch));
+    }
+}